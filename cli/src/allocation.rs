@@ -0,0 +1,236 @@
+use crate::chart::{self, Series};
+use crate::goal::EPIC_PREFIX;
+use crate::report::{self, estimate, OutputArgs, Table};
+use crate::Status;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use clap::Args;
+use divvee::config::{RepoConfig, CUSTOMER_PREFIX};
+use divvee::goal;
+use divvee::task::Task;
+use divvee::System;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Label prefixes that work is split by, with the heading of each split
+const SPLITS: [(&str, &str); 3] = [
+    ("Goal", goal::LABEL_PREFIX),
+    ("Epic", EPIC_PREFIX),
+    ("Customer", CUSTOMER_PREFIX),
+];
+
+#[derive(Args, Debug)]
+pub struct AllocationCmd {
+    /// Only include these teams. Defaults to every team in the repo
+    #[arg(long = "team", short = 't')]
+    teams: Vec<String>,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+/// Open and in-progress work per person, team and label split
+#[derive(Serialize)]
+struct Allocation {
+    generated: DateTime<Utc>,
+    teams: Vec<String>,
+    people: Vec<Row>,
+    by_team: Vec<Row>,
+    /// Rows per label of each split, e.g. `g-grow-self-serve` under `Goal`
+    splits: BTreeMap<&'static str, Vec<Row>>,
+}
+
+#[derive(Serialize, Default)]
+struct Row {
+    name: String,
+    todo: usize,
+    in_progress: usize,
+    /// Sum of the estimates of open work
+    estimate: f64,
+    /// Open tasks without an estimate
+    unestimated: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    capacity: Option<f64>,
+}
+
+impl Row {
+    fn new(name: &str) -> Row {
+        Row {
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn add(&mut self, task: &Task) {
+        match report::status(task) {
+            Status::InProgress => self.in_progress += 1,
+            _ => self.todo += 1,
+        }
+        match estimate(task) {
+            Some(estimate) => self.estimate += estimate,
+            None => self.unestimated += 1,
+        }
+    }
+
+    /// Estimate as a share of capacity
+    fn load(&self) -> Option<f64> {
+        self.capacity
+            .filter(|&capacity| capacity > 0.0)
+            .map(|capacity| self.estimate / capacity)
+    }
+
+    fn cells(&self, with_capacity: bool) -> Vec<String> {
+        let mut cells = vec![
+            self.name.clone(),
+            self.todo.to_string(),
+            self.in_progress.to_string(),
+            self.estimate.to_string(),
+            self.unestimated.to_string(),
+        ];
+        if with_capacity {
+            cells.push(self.capacity.map(|c| c.to_string()).unwrap_or_default());
+            cells.push(
+                self.load()
+                    .map(|load| format!("{:.0}%", load * 100.0))
+                    .unwrap_or_default(),
+            );
+        }
+        cells
+    }
+}
+
+pub async fn run(dv: &System, args: &AllocationCmd) -> Result<()> {
+    let teams = report::teams_or_all(dv, &args.teams)?;
+    let mut tasks = Vec::new();
+    for team in &teams {
+        let open = dv
+            .read_docs::<Task>(team)?
+            .into_iter()
+            .filter(|t| matches!(report::status(t), Status::Todo | Status::InProgress));
+        tasks.extend(open.map(|task| (team.clone(), task)));
+    }
+    if tasks.is_empty() {
+        bail!("No open work in {}", teams.join(", "));
+    }
+
+    let allocation = allocate(dv.config(), teams, &tasks);
+    args.output.write_report(
+        &allocation,
+        || allocation.to_text(),
+        || report::html_page("Allocation", &allocation.to_html()),
+    )
+}
+
+fn allocate(config: &RepoConfig, teams: Vec<String>, tasks: &[(String, Task)]) -> Allocation {
+    let mut people: BTreeMap<String, Row> = BTreeMap::new();
+    let mut by_team: BTreeMap<String, Row> = BTreeMap::new();
+    let mut splits: BTreeMap<&'static str, BTreeMap<String, Row>> = BTreeMap::new();
+    for (team, task) in tasks {
+        let person = task.assignee.as_deref().unwrap_or("(unassigned)");
+        people
+            .entry(person.to_owned())
+            .or_insert_with(|| Row {
+                capacity: config.people.get(person).and_then(|p| p.capacity),
+                ..Row::new(person)
+            })
+            .add(task);
+        by_team
+            .entry(team.clone())
+            .or_insert_with(|| Row {
+                capacity: config.team_capacity(team),
+                ..Row::new(team)
+            })
+            .add(task);
+        for (split, prefix) in SPLITS {
+            let mut labels: Vec<&str> = task
+                .labels
+                .iter()
+                .filter(|l| l.starts_with(prefix))
+                .map(String::as_str)
+                .collect();
+            if labels.is_empty() {
+                labels.push("(none)");
+            }
+            let rows = splits.entry(split).or_default();
+            for label in labels {
+                rows.entry(label.to_owned())
+                    .or_insert_with(|| Row::new(label))
+                    .add(task);
+            }
+        }
+    }
+    Allocation {
+        generated: Utc::now(),
+        teams,
+        people: people.into_values().collect(),
+        by_team: by_team.into_values().collect(),
+        splits: splits
+            .into_iter()
+            .map(|(split, rows)| (split, rows.into_values().collect()))
+            .collect(),
+    }
+}
+
+impl Allocation {
+    fn tables(&self) -> Vec<(String, Table)> {
+        let headers = |name: &str, with_capacity: bool| {
+            let mut headers = vec![name, "Todo", "In progress", "Estimate", "Unestimated"];
+            if with_capacity {
+                headers.extend(["Capacity", "Load"]);
+            }
+            Table::new(&headers)
+        };
+        let table = |name: &str, rows: &[Row], with_capacity: bool| {
+            let mut table = headers(name, with_capacity);
+            table.rows = rows.iter().map(|r| r.cells(with_capacity)).collect();
+            table
+        };
+        let mut sections = vec![
+            ("People".to_owned(), table("Person", &self.people, true)),
+            ("Teams".to_owned(), table("Team", &self.by_team, true)),
+        ];
+        for (split, _) in SPLITS {
+            if let Some(rows) = self.splits.get(split) {
+                sections.push((
+                    format!("By {}", split.to_lowercase()),
+                    table(split, rows, false),
+                ));
+            }
+        }
+        sections
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Open work in {}\n", self.teams.join(", "));
+        for (i, (title, table)) in self.tables().into_iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            let _ = writeln!(out, "{title}\n");
+            out.push_str(&table.to_text());
+        }
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let labels: Vec<String> = self.people.iter().map(|p| p.name.clone()).collect();
+        let series = [
+            Series::new(
+                "In Progress",
+                self.people.iter().map(|p| p.in_progress as f64).collect(),
+            ),
+            Series::new("Todo", self.people.iter().map(|p| p.todo as f64).collect()),
+        ];
+        let mut out = String::new();
+        for (i, (title, table)) in self.tables().into_iter().enumerate() {
+            let _ = writeln!(out, "<h2>{}</h2>", chart::escape(&title));
+            if i == 0 {
+                out.push_str(&chart::stacked_bars(&labels, &series));
+                out.push('\n');
+            }
+            out.push_str(&table.to_html());
+        }
+        out
+    }
+}
@@ -0,0 +1,119 @@
+use crate::report;
+use crate::util::end_of_day;
+use crate::Status;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Utc};
+use clap::{Args, ValueEnum};
+use divvee::collection::{self, Collection};
+use divvee::task::Task;
+use divvee::System;
+use log::warn;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct ArchiveCmd {
+    /// Archive tasks closed before this date (YYYY-MM-DD)
+    #[arg(long, conflicts_with = "older_than")]
+    before: Option<NaiveDate>,
+    /// Archive tasks closed more than this many days ago
+    #[arg(long, default_value_t = 90)]
+    older_than: u64,
+    /// Only archive these teams. Defaults to every team in the repo
+    #[arg(long = "team", short = 't')]
+    teams: Vec<String>,
+    /// Span of each archive dir
+    #[arg(long, value_enum, default_value_t = Period::Quarter)]
+    period: Period,
+    /// List the tasks that would be archived without moving them
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Period {
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Period {
+    /// Name of the archive dir of a date, e.g. `2025-07`, `2025-Q3` or `2025`
+    fn of(self, date: NaiveDate) -> String {
+        match self {
+            Period::Month => format!("{}-{:02}", date.year(), date.month()),
+            Period::Quarter => format!("{}-Q{}", date.year(), date.month0() / 3 + 1),
+            Period::Year => date.year().to_string(),
+        }
+    }
+}
+
+pub async fn run(dv: &mut System, args: ArchiveCmd) -> Result<()> {
+    let cutoff: DateTime<Utc> = match args.before {
+        Some(date) => end_of_day(date - Days::new(1)),
+        None => Utc::now() - Days::new(args.older_than),
+    };
+    let teams = report::teams_or_all(dv, &args.teams)?;
+
+    let mut total = 0;
+    for team in &teams {
+        let tasks = closed_before(dv, team, cutoff, args.period)?;
+        if tasks.is_empty() {
+            continue;
+        }
+        total += tasks.len();
+        if args.dry_run {
+            for (id, period) in &tasks {
+                println!(
+                    "{id} -> {}",
+                    collection::archive_dir(team).join(period).display()
+                );
+            }
+            continue;
+        }
+        let (archived, skipped) = dv.archive_tasks(team, &tasks).await?;
+        for (id, err) in skipped {
+            warn!("Skipping {id}: {err}");
+        }
+        let mut by_dir: BTreeMap<PathBuf, usize> = BTreeMap::new();
+        for path in archived {
+            *by_dir
+                .entry(path.parent().unwrap_or(&path).to_owned())
+                .or_default() += 1;
+        }
+        for (dir, count) in by_dir {
+            println!("Archived {count} to {}", dir.display());
+        }
+    }
+    if total == 0 {
+        bail!("No closed tasks to archive");
+    }
+    Ok(())
+}
+
+/// IDs of the team's tasks closed before `cutoff`, with the period each is archived under
+///
+/// A closed task's last change is taken as when it was closed.
+fn closed_before(
+    dv: &System,
+    team: &str,
+    cutoff: DateTime<Utc>,
+    period: Period,
+) -> Result<Vec<(String, String)>> {
+    let mut tasks = Vec::new();
+    for task in dv.read_docs::<Task>(team)? {
+        if matches!(report::status(&task), Status::Todo | Status::InProgress) {
+            continue;
+        }
+        let Some(id) = task.id() else {
+            continue;
+        };
+        let path = dv.doc_path(Task::KIND.dir(team), &id);
+        let meta = dv.read_doc_with_meta::<Task, _>(path)?;
+        let closed = meta.updated.as_ref().unwrap_or(&meta.created).timestamp;
+        if closed < cutoff {
+            tasks.push((id, period.of(closed.with_timezone(&Local).date_naive())));
+        }
+    }
+    Ok(tasks)
+}
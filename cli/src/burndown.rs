@@ -0,0 +1,251 @@
+use crate::chart::{self, Series};
+use crate::report::{self, estimate, OutputArgs, ReportOutput, Table, TaskHistory};
+use crate::{config, util, Status};
+use anyhow::{bail, Context, Result};
+use chrono::{Local, NaiveDate, Utc};
+use clap::Args;
+use divvee::task::Task;
+use divvee::System;
+use serde::Serialize;
+use std::fmt::Write;
+
+#[derive(Args, Debug)]
+#[group(id = "scope", required = true, multiple = false)]
+pub struct BurndownCmd {
+    /// Sprint label (e.g. s-alpha)
+    #[arg(long, group = "scope")]
+    sprint: Option<String>,
+    /// Milestone label (e.g. m-launch)
+    #[arg(long, group = "scope")]
+    milestone: Option<String>,
+    #[arg(long, short = 't')]
+    team: Option<String>,
+    /// First day of the chart. Defaults to when the label was first used
+    #[arg(long)]
+    from: Option<NaiveDate>,
+    /// Last day of the chart (e.g. the end of the sprint). Defaults to today
+    #[arg(long)]
+    to: Option<NaiveDate>,
+    /// Count tasks instead of summing their estimate prop
+    #[arg(long)]
+    count: bool,
+    /// Chart completed work against total scope instead of remaining work
+    #[arg(long)]
+    burnup: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Serialize)]
+struct Burndown {
+    label: String,
+    unit: &'static str,
+    days: Vec<Day>,
+}
+
+/// Work on a label at the end of a day. Days after today have no values.
+#[derive(Serialize)]
+struct Day {
+    date: NaiveDate,
+    remaining: Option<f64>,
+    completed: Option<f64>,
+    scope: Option<f64>,
+    ideal: f64,
+}
+
+pub fn run(dv: &System, args: &BurndownCmd) -> Result<()> {
+    let label = args.sprint.as_ref().or(args.milestone.as_ref()).unwrap();
+    let team = match &args.team {
+        Some(team) => team.clone(),
+        None => config::default_team()?,
+    };
+    let history: Vec<TaskHistory> = report::load_history(dv, &team)?
+        .into_iter()
+        .filter(|h| {
+            h.revisions
+                .iter()
+                .any(|r| r.doc.as_ref().is_some_and(|t| has_label(t, label)))
+        })
+        .collect();
+    if history.is_empty() {
+        bail!("No {} tasks were ever labeled {}", team, label);
+    }
+
+    let today = Local::now().date_naive();
+    let from = match args.from {
+        Some(from) => from,
+        None => first_labeled(&history, label)
+            .context("No revisions found")?
+            .with_timezone(&Local)
+            .date_naive(),
+    };
+    let to = args.to.unwrap_or(today).max(from);
+
+    let use_count = args.count
+        || !history
+            .iter()
+            .any(|h| h.current().is_some_and(|t| estimate(t).is_some()));
+    let weight = |task: &Task| match use_count {
+        true => 1.0,
+        false => estimate(task).unwrap_or(0.0),
+    };
+
+    let mut days = Vec::new();
+    for date in from.iter_days().take_while(|d| *d <= to) {
+        if date > today {
+            days.push(Day {
+                date,
+                remaining: None,
+                completed: None,
+                scope: None,
+                ideal: 0.0,
+            });
+            continue;
+        }
+        let time = match date == today {
+            true => Utc::now(),
+            false => util::end_of_day(date),
+        };
+        let (mut remaining, mut completed) = (0.0, 0.0);
+        for task in history
+            .iter()
+            .filter_map(|h| h.at(time))
+            .filter(|t| has_label(t, label))
+        {
+            match report::status(task) {
+                Status::Todo | Status::InProgress => remaining += weight(task),
+                Status::Done => completed += weight(task),
+                // canceled work drops out of scope
+                Status::Canceled | Status::Duplicate => {}
+            }
+        }
+        days.push(Day {
+            date,
+            remaining: Some(remaining),
+            completed: Some(completed),
+            scope: Some(remaining + completed),
+            ideal: 0.0,
+        });
+    }
+
+    // ideal lines run straight to zero remaining, or the current scope, on the last day
+    let start = days[0].remaining.unwrap_or(0.0);
+    let scope = days.iter().rev().find_map(|d| d.scope).unwrap_or(0.0);
+    let span = (days.len() - 1).max(1) as f64;
+    for (i, day) in days.iter_mut().enumerate() {
+        day.ideal = match args.burnup {
+            true => scope * i as f64 / span,
+            false => start * (1.0 - i as f64 / span),
+        };
+    }
+
+    let burndown = Burndown {
+        label: label.clone(),
+        unit: if use_count { "tasks" } else { "estimate" },
+        days,
+    };
+    let content = match args.output.output {
+        ReportOutput::Text => burndown.to_text(args.burnup),
+        ReportOutput::Json => format!("{}\n", serde_json::to_string_pretty(&burndown)?),
+        ReportOutput::Html => {
+            let body = format!(
+                "{}\n{}",
+                burndown.to_svg(args.burnup),
+                burndown.table().to_html()
+            );
+            report::html_page(&format!("{} {}", label, burndown.title(args.burnup)), &body)
+        }
+        ReportOutput::Svg => format!("{}\n", burndown.to_svg(args.burnup)),
+    };
+    report::write_output(args.output.out.as_deref(), &content)
+}
+
+/// Whether a task carries the label, ignoring case like SLA rules do
+pub fn has_label(task: &Task, label: &str) -> bool {
+    task.labels.iter().any(|l| l.eq_ignore_ascii_case(label))
+}
+
+/// Time of the first revision of any task that carried the label
+pub fn first_labeled(history: &[TaskHistory], label: &str) -> Option<chrono::DateTime<Utc>> {
+    history
+        .iter()
+        .flat_map(|h| &h.revisions)
+        .filter(|r| r.doc.as_ref().is_some_and(|t| has_label(t, label)))
+        .map(|r| r.change.timestamp)
+        .min()
+}
+
+impl Burndown {
+    fn title(&self, burnup: bool) -> &'static str {
+        match burnup {
+            true => "burnup",
+            false => "burndown",
+        }
+    }
+
+    fn labels(&self) -> Vec<String> {
+        self.days
+            .iter()
+            .map(|d| d.date.format("%m-%d").to_string())
+            .collect()
+    }
+
+    fn series(&self, burnup: bool) -> Vec<Series> {
+        let values = |f: fn(&Day) -> Option<f64>| {
+            self.days.iter().map(|d| f(d).unwrap_or(f64::NAN)).collect()
+        };
+        let ideal = Series::new("Ideal", self.days.iter().map(|d| d.ideal).collect());
+        match burnup {
+            true => vec![
+                Series::new("Scope", values(|d| d.scope)),
+                Series::new("Completed", values(|d| d.completed)),
+                ideal,
+            ],
+            false => vec![Series::new("Remaining", values(|d| d.remaining)), ideal],
+        }
+    }
+
+    fn to_svg(&self, burnup: bool) -> String {
+        chart::lines(&self.labels(), &self.series(burnup))
+    }
+
+    fn table(&self) -> Table {
+        let mut table = Table::new(&["Date", "Remaining", "Completed", "Scope", "Ideal"]);
+        let fmt = |v: Option<f64>| v.map(|v| format!("{v}")).unwrap_or_default();
+        for day in &self.days {
+            table.rows.push(vec![
+                day.date.to_string(),
+                fmt(day.remaining),
+                fmt(day.completed),
+                fmt(day.scope),
+                format!("{:.1}", day.ideal),
+            ]);
+        }
+        table
+    }
+
+    fn to_text(&self, burnup: bool) -> String {
+        let values: Vec<f64> = self
+            .series(burnup)
+            .remove(match burnup {
+                true => 1,
+                false => 0,
+            })
+            .values;
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{} {} ({}, {} to {})\n",
+            self.label,
+            self.title(burnup),
+            self.unit,
+            self.days[0].date,
+            self.days[self.days.len() - 1].date,
+        );
+        let _ = writeln!(out, "{}\n", chart::sparkline(&values));
+        out.push_str(&chart::text_bars(&values, 10));
+        out.push('\n');
+        out.push_str(&self.table().to_text());
+        out
+    }
+}
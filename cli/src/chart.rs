@@ -0,0 +1,194 @@
+//! Minimal charts: SVG for HTML reports and plain text for the terminal
+
+use std::fmt::Write;
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 260.0;
+const LEFT: f64 = 40.0;
+const RIGHT: f64 = 10.0;
+const TOP: f64 = 30.0;
+const BOTTOM: f64 = 40.0;
+const COLORS: [&str; 6] = [
+    "#4e79a7", "#f28e2b", "#59a14f", "#e15759", "#b07aa1", "#9c755f",
+];
+
+/// A named set of values, one per chart label
+pub struct Series {
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+impl Series {
+    pub fn new(name: impl Into<String>, values: Vec<f64>) -> Series {
+        Series {
+            name: name.into(),
+            values,
+        }
+    }
+}
+
+/// Bar chart with one bar per label, stacking each series
+pub fn stacked_bars(labels: &[String], series: &[Series]) -> String {
+    let totals: Vec<f64> = (0..labels.len())
+        .map(|i| series.iter().map(|s| s.values[i]).sum())
+        .collect();
+    let max = totals.iter().copied().fold(0.0, f64::max).max(1.0);
+    let slot = (WIDTH - LEFT - RIGHT) / labels.len().max(1) as f64;
+
+    let mut svg = open_svg();
+    axes(&mut svg, labels, max);
+    for i in 0..labels.len() {
+        let mut base = 0.0;
+        for (s, color) in series.iter().zip(COLORS.iter().cycle()) {
+            let value = s.values[i];
+            if value > 0.0 {
+                let (y0, y1) = (y(base, max), y(base + value, max));
+                let _ = write!(
+                    svg,
+                    r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{color}"><title>{}: {value}</title></rect>"#,
+                    LEFT + slot * i as f64 + slot * 0.1,
+                    y1,
+                    slot * 0.8,
+                    y0 - y1,
+                    escape(&s.name),
+                );
+            }
+            base += value;
+        }
+    }
+    legend(&mut svg, series);
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Line chart with one point per label for each series
+///
+/// NaN values leave a gap, e.g. for days that haven't happened yet.
+pub fn lines(labels: &[String], series: &[Series]) -> String {
+    let max = series
+        .iter()
+        .flat_map(|s| s.values.iter().copied())
+        .filter(|v| !v.is_nan())
+        .fold(0.0, f64::max)
+        .max(1.0);
+    let slot = (WIDTH - LEFT - RIGHT) / labels.len().max(1) as f64;
+
+    let mut svg = open_svg();
+    axes(&mut svg, labels, max);
+    for (s, color) in series.iter().zip(COLORS.iter().cycle()) {
+        let points: Vec<String> = s
+            .values
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| !v.is_nan())
+            .map(|(i, v)| format!("{:.1},{:.1}", LEFT + slot * (i as f64 + 0.5), y(*v, max)))
+            .collect();
+        let _ = write!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{color}" stroke-width="2"/>"#,
+            points.join(" ")
+        );
+    }
+    legend(&mut svg, series);
+    svg.push_str("</svg>");
+    svg
+}
+
+/// One block character per value, scaled to the largest value
+pub fn sparkline(values: &[f64]) -> String {
+    const TICKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().fold(0.0, f64::max);
+    values
+        .iter()
+        .map(|&v| match v.is_nan() {
+            true => ' ',
+            false if max <= 0.0 => TICKS[0],
+            false => TICKS[((v / max) * 7.0).round() as usize],
+        })
+        .collect()
+}
+
+/// Vertical bar chart drawn with text, one column per value
+pub fn text_bars(values: &[f64], height: usize) -> String {
+    let max = values.iter().copied().fold(0.0, f64::max).max(1.0);
+    let label_width = format!("{max}").len();
+    let mut out = String::new();
+    for row in (1..=height).rev() {
+        let threshold = max * (row as f64 - 0.5) / height as f64;
+        let axis = match row {
+            r if r == height => format!("{max:>label_width$}"),
+            1 => format!("{:>label_width$}", 0),
+            _ => " ".repeat(label_width),
+        };
+        let bars: String = values
+            .iter()
+            .map(|&v| if v >= threshold { '█' } else { ' ' })
+            .collect();
+        let _ = writeln!(out, "{axis} │{}", bars.trim_end());
+    }
+    let _ = writeln!(
+        out,
+        "{} └{}",
+        " ".repeat(label_width),
+        "─".repeat(values.len())
+    );
+    out
+}
+
+fn open_svg() -> String {
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {WIDTH} {HEIGHT}" width="{WIDTH}" height="{HEIGHT}" font-family="sans-serif" font-size="10">"#
+    )
+}
+
+fn y(value: f64, max: f64) -> f64 {
+    HEIGHT - BOTTOM - value / max * (HEIGHT - TOP - BOTTOM)
+}
+
+fn axes(svg: &mut String, labels: &[String], max: f64) {
+    for tick in [0.0, max / 2.0, max] {
+        let _ = write!(
+            svg,
+            r##"<line x1="{LEFT}" x2="{:.1}" y1="{y:.1}" y2="{y:.1}" stroke="#ddd"/><text x="{:.1}" y="{:.1}" text-anchor="end">{}</text>"##,
+            WIDTH - RIGHT,
+            LEFT - 4.0,
+            y(tick, max) + 3.0,
+            (tick * 10.0).round() / 10.0,
+            y = y(tick, max),
+        );
+    }
+    let slot = (WIDTH - LEFT - RIGHT) / labels.len().max(1) as f64;
+    let step = labels.len().div_ceil(12).max(1);
+    for (i, label) in labels.iter().enumerate().step_by(step) {
+        let _ = write!(
+            svg,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+            LEFT + slot * (i as f64 + 0.5),
+            HEIGHT - BOTTOM + 14.0,
+            escape(label),
+        );
+    }
+}
+
+fn legend(svg: &mut String, series: &[Series]) {
+    if series.len() < 2 {
+        return;
+    }
+    let mut x = LEFT;
+    for (s, color) in series.iter().zip(COLORS.iter().cycle()) {
+        let _ = write!(
+            svg,
+            r#"<rect x="{x:.1}" y="8" width="10" height="10" fill="{color}"/><text x="{:.1}" y="17">{}</text>"#,
+            x + 14.0,
+            escape(&s.name),
+        );
+        x += 24.0 + 6.0 * s.name.len() as f64;
+    }
+}
+
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
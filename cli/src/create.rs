@@ -1,11 +1,20 @@
-use crate::config;
-use anyhow::Result;
+use crate::{config, util};
+use anyhow::{bail, Context, Result};
+use chrono::Local;
 use clap::Args;
-use divvee::task::Task;
+use divvee::collection::Collection;
+use divvee::format::{self, DocFormat};
+use divvee::task::{Priority, Task};
 use divvee::*;
 use log::debug;
-use std::path::Path;
-use tempfile::Builder;
+use minijinja::Environment;
+use serde::Deserialize;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// Template used when `--template` isn't given
+const DEFAULT_TEMPLATE: &str = "task";
 
 #[derive(Args, Debug)]
 pub struct CreateCmd {
@@ -14,6 +23,23 @@ pub struct CreateCmd {
     team: Option<String>,
     #[arg(long, short = 'd')]
     description: Option<String>,
+    /// Start from a named template, e.g. `bug` for TEAM/tasks/_bug.md
+    #[arg(long, short = 'T')]
+    template: Option<String>,
+    /// Task type (e.g. bug). Uses the template of the same name if there is one
+    #[arg(long = "type")]
+    kind: Option<String>,
+}
+
+/// Template-only front matter that isn't part of the task
+#[derive(Debug, Default, Deserialize)]
+struct TemplateMeta {
+    /// Fields to prompt for if the template and editor leave them empty
+    ///
+    /// Only covers the fields listed here. Props required by the repo config or the task's
+    /// type are checked when the task is created.
+    #[serde(default)]
+    required: Vec<String>,
 }
 
 pub async fn run(dv: &mut System, args: CreateCmd) -> Result<()> {
@@ -21,29 +47,50 @@ pub async fn run(dv: &mut System, args: CreateCmd) -> Result<()> {
         Some(team) => team,
         None => config::default_team()?,
     };
-    let rel_dir = Path::new(&team).join("tasks");
+    let rel_dir = Task::KIND.dir(&team);
     debug!("create rel_dir: {}", rel_dir.display());
 
-    let mut new_task: Task = dv
-        .read_doc(rel_dir.join("_task.md"))
-        .ok()
-        .unwrap_or_else(Task::default);
+    let template = match (&args.template, &args.kind) {
+        (None, Some(kind)) if template_path(dv, &rel_dir, kind).exists() => Some(kind.as_str()),
+        (template, _) => template.as_deref(),
+    };
+    let (mut new_task, meta) = match read_template(dv, &rel_dir, template)? {
+        Some(source) => {
+            let rendered = render_template(dv, &source)?;
+            let meta = match format::doc_fields(&rendered, None, &["required"]) {
+                Some(fields) => {
+                    serde_yaml::from_value(fields.into()).context("Invalid template")?
+                }
+                None => TemplateMeta::default(),
+            };
+            (Task::parse_doc(&rendered, None)?, meta)
+        }
+        None => (Task::default(), TemplateMeta::default()),
+    };
     new_task.title = args.title;
+    if args.kind.is_some() {
+        new_task.kind = args.kind;
+    }
+    new_task.apply_defaults(dv.config());
+    let team_id = dv.next_doc_id::<Task>(&team, &new_task.title)?;
+    let path = dv.doc_path(&rel_dir, &team_id);
     if let Some(description) = args.description {
         if !description.is_empty() {
             new_task.description = Some(description);
         }
     } else {
-        let template = new_task
-            .to_doc_string()
-            .replace("\n---\n", "\n# Provide description below dashed line\n---");
-        let editted = edit::edit_with_builder(template, Builder::new().suffix(".md"))?;
-        new_task = Task::parse_doc(&editted, None)?;
+        let template = match dv.config().doc_format(&rel_dir) {
+            DocFormat::Yaml => new_task
+                .to_doc_string()
+                .replace("\n---\n", "\n# Provide description below dashed line\n---"),
+            format => new_task.to_doc_string_as(format),
+        };
+        new_task = util::edit_doc(template, |s| Task::parse_valid(s, &path, dv.config()))?;
     }
+    // placeholders that rendered empty, e.g. `{{sprint.current}}` outside of a sprint
+    new_task.labels.retain(|label| !label.is_empty());
+    prompt_required(&mut new_task, &meta.required)?;
 
-    let id = dv.next_id(&rel_dir)?;
-    let team_id = format!("{}-{}", &team, id);
-    let path = rel_dir.join(&team_id).with_extension("md");
     debug!("Creating doc at {}", path.display());
     let doc = dv.create_doc(path, new_task).await?;
 
@@ -51,3 +98,133 @@ pub async fn run(dv: &mut System, args: CreateCmd) -> Result<()> {
     println!("Created {}", task.id().unwrap());
     Ok(())
 }
+
+/// Reads `_<name>.md` (or `.kdl`) from the team's task dir
+///
+/// A missing default template is fine, but a missing named template is an error.
+fn read_template(dv: &System, rel_dir: &Path, name: Option<&str>) -> Result<Option<String>> {
+    let path = template_path(dv, rel_dir, name.unwrap_or(DEFAULT_TEMPLATE));
+    match (path.exists(), name) {
+        (true, _) => {
+            debug!("Using template {}", path.display());
+            Ok(Some(fs::read_to_string(path)?))
+        }
+        (false, None) => Ok(None),
+        (false, Some(name)) => {
+            let available: Vec<String> = fs::read_dir(dv.root().join(rel_dir))?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| is_template(p))
+                .filter_map(|p| {
+                    p.file_stem()
+                        .map(|s| s.to_string_lossy()[TEMPLATE_PREFIX.len_utf8()..].to_owned())
+                })
+                .collect();
+            match available.is_empty() {
+                true => bail!("No template named {}", name),
+                false => bail!(
+                    "No template named {}. Available: {}",
+                    name,
+                    available.join(", ")
+                ),
+            }
+        }
+    }
+}
+
+fn template_path(dv: &System, rel_dir: &Path, name: &str) -> PathBuf {
+    let path = dv.doc_path(rel_dir, &format!("{TEMPLATE_PREFIX}{name}"));
+    dv.root().join(path)
+}
+
+/// Fills in `{{me}}`, `{{today}}` and `{{sprint.current}}`
+fn render_template(dv: &System, source: &str) -> Result<String> {
+    let today = Local::now().date_naive();
+    let current = dv.config().current_sprint(today).map(|s| s.label.clone());
+    let mut env = Environment::new();
+    env.set_keep_trailing_newline(true);
+    env.add_template("task", source)?;
+    let rendered = env.get_template("task")?.render(minijinja::context! {
+        me => config::me().email,
+        today => today.to_string(),
+        sprint => minijinja::context! { current },
+    })?;
+    Ok(rendered)
+}
+
+fn prompt_required(task: &mut Task, required: &[String]) -> Result<()> {
+    let missing: Vec<&String> = required.iter().filter(|f| is_missing(task, f)).collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        let missing: Vec<&str> = missing.iter().map(|f| f.as_str()).collect();
+        bail!("Missing required fields: {}", missing.join(", "));
+    }
+    for field in missing {
+        // the same prop may be required as both `severity` and `props.severity`
+        while is_missing(task, field) {
+            print!("{field}: ");
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            io::stdin().lock().read_line(&mut answer)?;
+            let answer = answer.trim();
+            if answer.is_empty() {
+                continue;
+            }
+            if let Err(err) = set_field(task, field, answer) {
+                println!("{err}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_missing(task: &Task, field: &str) -> bool {
+    match field {
+        "title" => task.title.is_empty(),
+        "status" => task.status.is_none(),
+        "assignee" => task.assignee.is_none(),
+        "priority" => task.priority.is_none(),
+        "start" => task.start.is_none(),
+        "due" => task.due.is_none(),
+        "labels" => task.labels.is_empty(),
+        "description" => task.description.is_none(),
+        "type" => task.kind.is_none(),
+        prop => task
+            .props
+            .get(prop.strip_prefix("props.").unwrap_or(prop))
+            .is_none_or(|v| v.is_null() || v.as_str() == Some("")),
+    }
+}
+
+fn set_field(task: &mut Task, field: &str, value: &str) -> Result<()> {
+    match field {
+        "title" => task.title = value.to_owned(),
+        "status" => task.status = Some(value.parse::<crate::Status>()?.to_string()),
+        "assignee" => {
+            task.assignee = match value {
+                "me" => Some(config::me().email.to_owned()),
+                _ => Some(value.to_owned()),
+            }
+        }
+        "priority" => task.priority = value.parse::<Priority>()?,
+        "start" => task.start = util::parse_date(value)?,
+        "due" => task.due = util::parse_date(value)?,
+        "labels" => {
+            task.labels = value
+                .split(',')
+                .map(|l| l.trim().to_owned())
+                .filter(|l| !l.is_empty())
+                .collect()
+        }
+        "description" => task.description = Some(value.to_owned()),
+        "type" => task.kind = Some(value.to_owned()),
+        prop => {
+            let prop = prop.strip_prefix("props.").unwrap_or(prop);
+            let value = serde_yaml::from_str(value)
+                .unwrap_or_else(|_| serde_yaml::Value::String(value.to_owned()));
+            task.props.insert(prop.to_owned(), value);
+        }
+    }
+    Ok(())
+}
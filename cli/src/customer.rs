@@ -0,0 +1,297 @@
+use crate::report::{self, left_aligned, OutputArgs, Table, TaskHistory};
+use crate::Status;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use clap::Args;
+use divvee::config::{RepoConfig, CUSTOMER_PREFIX};
+use divvee::System;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+#[derive(Args, Debug)]
+pub struct CustomerCmd {
+    /// Customer key, display name or alias. Summarizes every customer when omitted
+    customer: Option<String>,
+    /// Number of days to look back for shipped work
+    #[arg(long, short = 'd', default_value_t = 30)]
+    days: u64,
+    /// Only include these teams. Defaults to every team in the repo
+    #[arg(long = "team", short = 't')]
+    teams: Vec<String>,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+/// Open requests and recently shipped work for one customer
+#[derive(Serialize)]
+struct CustomerStatus {
+    generated: DateTime<Utc>,
+    customer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tier: Option<String>,
+    /// Shipped work is completed within this many days
+    days: u64,
+    open: Vec<Request>,
+    shipped: Vec<Request>,
+}
+
+#[derive(Serialize, Clone)]
+struct Request {
+    id: String,
+    title: String,
+    status: String,
+    #[serde(skip)]
+    state: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignee: Option<String>,
+    created: NaiveDate,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    completed: Option<NaiveDate>,
+    /// Days since the request was created
+    age: i64,
+}
+
+/// One row per customer when no customer is given
+#[derive(Serialize)]
+struct Summary {
+    customer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tier: Option<String>,
+    open: usize,
+    in_progress: usize,
+    /// Age in days of the oldest open request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    oldest: Option<i64>,
+    shipped: usize,
+}
+
+pub async fn run(dv: &System, args: &CustomerCmd) -> Result<()> {
+    let config = dv.config();
+    let teams = report::teams_or_all(dv, &args.teams)?;
+    let now = Utc::now();
+    let since = now - Days::new(args.days);
+    let mut by_customer: BTreeMap<String, (Vec<Request>, Vec<Request>)> = BTreeMap::new();
+    for team in &teams {
+        for history in report::load_history(dv, team)? {
+            let Some(request) = request(&history, now) else {
+                continue;
+            };
+            let task = history.current().expect("request has a current revision");
+            let shipped = history.completed().is_some_and(|t| t >= since);
+            for label in &task.labels {
+                let Some(name) = label.strip_prefix(CUSTOMER_PREFIX) else {
+                    continue;
+                };
+                let key = config.customer_key(name).unwrap_or(name);
+                let (open, recent) = by_customer.entry(key.to_owned()).or_default();
+                match request.state {
+                    Status::Todo | Status::InProgress => open.push(request.clone()),
+                    _ if shipped => recent.push(request.clone()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    match &args.customer {
+        Some(customer) => {
+            let key = config
+                .customer_key(customer)
+                .unwrap_or(customer.strip_prefix(CUSTOMER_PREFIX).unwrap_or(customer));
+            if !config.customers.contains_key(key) && !by_customer.contains_key(key) {
+                bail!("Unknown customer {customer}");
+            }
+            let (mut open, mut shipped) = by_customer.remove(key).unwrap_or_default();
+            open.sort_by_key(|r| std::cmp::Reverse(r.age));
+            shipped.sort_by_key(|r| std::cmp::Reverse(r.completed));
+            let customer = config.customers.get(key);
+            let status = CustomerStatus {
+                generated: now,
+                customer: key.to_owned(),
+                name: customer.and_then(|c| c.name.clone()),
+                tier: customer.and_then(|c| c.tier.clone()),
+                days: args.days,
+                open,
+                shipped,
+            };
+            args.output.write_report(
+                &status,
+                || status.to_text(),
+                || report::html_page(&status.title(), &status.to_html()),
+            )
+        }
+        None => {
+            let summaries = summarize(config, by_customer);
+            if summaries.is_empty() {
+                bail!(
+                    "No customers in the config or on tasks in {}",
+                    teams.join(", ")
+                );
+            }
+            let table = summary_table(&summaries, args.days);
+            args.output.write_report(
+                &summaries,
+                || table.to_text(),
+                || report::html_page("Customers", &table.to_html()),
+            )
+        }
+    }
+}
+
+/// Current state of a task with its dates from history
+fn request(history: &TaskHistory, now: DateTime<Utc>) -> Option<Request> {
+    let task = history.current()?;
+    let created = history.created()?;
+    Some(Request {
+        id: task.id()?,
+        title: task.title.clone(),
+        status: report::status(task).to_string(),
+        state: report::status(task),
+        assignee: task.assignee.clone(),
+        created: created.date_naive(),
+        due: task.due,
+        completed: history.completed().map(|t| t.date_naive()),
+        age: (now - created).num_days(),
+    })
+}
+
+/// Summary rows for every registered customer and every customer label in use
+fn summarize(
+    config: &RepoConfig,
+    mut by_customer: BTreeMap<String, (Vec<Request>, Vec<Request>)>,
+) -> Vec<Summary> {
+    for key in config.customers.keys() {
+        by_customer.entry(key.clone()).or_default();
+    }
+    by_customer
+        .into_iter()
+        .map(|(key, (open, shipped))| {
+            let customer = config.customers.get(&key);
+            Summary {
+                name: customer.and_then(|c| c.name.clone()),
+                tier: customer.and_then(|c| c.tier.clone()),
+                customer: key,
+                open: open.len(),
+                in_progress: open
+                    .iter()
+                    .filter(|r| r.state == Status::InProgress)
+                    .count(),
+                oldest: open.iter().map(|r| r.age).max(),
+                shipped: shipped.len(),
+            }
+        })
+        .collect()
+}
+
+fn summary_table(summaries: &[Summary], days: u64) -> Table {
+    let shipped = format!("Shipped ({days}d)");
+    let mut table = Table::new(&[
+        "Customer",
+        "Tier",
+        "Open",
+        "In progress",
+        "Oldest (days)",
+        &shipped,
+    ]);
+    table.rows = summaries
+        .iter()
+        .map(|s| {
+            vec![
+                s.name.clone().unwrap_or_else(|| s.customer.clone()),
+                s.tier.clone().unwrap_or_default(),
+                s.open.to_string(),
+                s.in_progress.to_string(),
+                s.oldest.map(|d| d.to_string()).unwrap_or_default(),
+                s.shipped.to_string(),
+            ]
+        })
+        .collect();
+    table
+}
+
+impl CustomerStatus {
+    fn title(&self) -> String {
+        let name = self.name.as_deref().unwrap_or(&self.customer);
+        match &self.tier {
+            Some(tier) => format!("{name} ({tier})"),
+            None => name.to_owned(),
+        }
+    }
+
+    fn open_table(&self) -> Table {
+        let mut table = Table::new(&["ID", "Status", "Age", "Assignee", "Due", "Title"]);
+        table.rows = self
+            .open
+            .iter()
+            .map(|r| {
+                vec![
+                    r.id.clone(),
+                    r.status.clone(),
+                    format!("{}d", r.age),
+                    r.assignee.clone().unwrap_or_default(),
+                    r.due.map(|d| d.to_string()).unwrap_or_default(),
+                    r.title.clone(),
+                ]
+            })
+            .collect();
+        table
+    }
+
+    fn shipped_table(&self) -> Table {
+        let mut table = Table::new(&["ID", "Completed", "Assignee", "Title"]);
+        table.rows = self
+            .shipped
+            .iter()
+            .map(|r| {
+                vec![
+                    r.id.clone(),
+                    r.completed.map(|d| d.to_string()).unwrap_or_default(),
+                    r.assignee.clone().unwrap_or_default(),
+                    r.title.clone(),
+                ]
+            })
+            .collect();
+        table
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{}\n", self.title());
+        let _ = writeln!(out, "Open requests ({})\n", self.open.len());
+        if !self.open.is_empty() {
+            out.push_str(&left_aligned(&self.open_table()));
+            out.push('\n');
+        }
+        let _ = writeln!(
+            out,
+            "Shipped in the last {} days ({})\n",
+            self.days,
+            self.shipped.len()
+        );
+        if !self.shipped.is_empty() {
+            out.push_str(&left_aligned(&self.shipped_table()));
+        }
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "<h2>Open requests ({})</h2>", self.open.len());
+        out.push_str(&self.open_table().to_html());
+        let _ = writeln!(
+            out,
+            "<h2>Shipped in the last {} days ({})</h2>",
+            self.days,
+            self.shipped.len()
+        );
+        out.push_str(&self.shipped_table().to_html());
+        out
+    }
+}
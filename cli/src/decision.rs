@@ -0,0 +1,256 @@
+use crate::{config, report, util, FilterArgs, Labels, Status, StatusFilter};
+use anyhow::{bail, Result};
+use chrono::Local;
+use clap::{Args, Subcommand};
+use divvee::collection::Collection;
+use divvee::decision::{Decision, DecisionStatus};
+use divvee::task::Task;
+use divvee::{RepoDoc, System};
+use log::debug;
+use owo_colors::OwoColorize;
+use termimad::MadSkin;
+
+#[derive(Args, Debug)]
+pub struct DecisionCmd {
+    #[command(subcommand)]
+    action: DecisionAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum DecisionAction {
+    /// Propose a decision. Tasks labeled `d-<id>` wait on it until it's accepted
+    New(NewArgs),
+    /// List decisions with how many of their tasks are still open
+    List(ListArgs),
+    /// Show a decision and the tasks that depend on it
+    Show(ShowArgs),
+    /// Accept a decision, unblocking the tasks that depend on it
+    Accept(AcceptArgs),
+    /// Mark a decision as replaced by another one
+    Supersede(SupersedeArgs),
+}
+
+#[derive(Args, Debug)]
+struct NewArgs {
+    title: String,
+    #[arg(long, short = 't')]
+    team: Option<String>,
+    /// Background of the decision. Opens the editor if not given
+    #[arg(long, short = 'c')]
+    context: Option<String>,
+    /// An option under consideration. Can be given more than once
+    #[arg(long = "option")]
+    options: Vec<String>,
+    /// Who makes the call. Defaults to you
+    #[arg(long)]
+    owner: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct ListArgs {
+    #[arg(long, short = 's')]
+    status: Option<DecisionStatus>,
+}
+
+#[derive(Args, Debug)]
+struct ShowArgs {
+    id: String,
+}
+
+#[derive(Args, Debug)]
+struct AcceptArgs {
+    id: String,
+    /// What was decided, usually one of the options
+    #[arg(long)]
+    outcome: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct SupersedeArgs {
+    id: String,
+    /// ID of the decision that replaces it
+    #[arg(long, required = true)]
+    by: String,
+}
+
+pub async fn run(dv: &mut System, args: DecisionCmd) -> Result<()> {
+    match args.action {
+        DecisionAction::New(args) => new(dv, args).await,
+        DecisionAction::List(args) => list(dv, args).await,
+        DecisionAction::Show(args) => show(dv, args).await,
+        DecisionAction::Accept(args) => accept(dv, args).await,
+        DecisionAction::Supersede(args) => supersede(dv, args).await,
+    }
+}
+
+async fn new(dv: &mut System, args: NewArgs) -> Result<()> {
+    let team = match args.team {
+        Some(team) => team,
+        None => config::default_team()?,
+    };
+    let rel_dir = Decision::KIND.dir(&team);
+    let mut decision = Decision::new(&args.title);
+    decision.options = args.options;
+    decision.owner = Some(args.owner.unwrap_or_else(|| config::me().email.clone()));
+    match args.context {
+        Some(context) => decision.context = Some(context).filter(|c| !c.is_empty()),
+        None => {
+            let template = decision.to_doc_string_as(dv.config().doc_format(&rel_dir));
+            decision = util::edit_doc(template, |s| Decision::parse_doc(s, None))?;
+        }
+    }
+
+    let id = dv.next_doc_id::<Decision>(&team, &decision.title)?;
+    let path = dv.doc_path(&rel_dir, &id);
+    debug!("Creating decision at {}", path.display());
+    let decision = dv
+        .create_doc(path, decision)
+        .await?
+        .read_doc::<Decision>()?;
+    println!(
+        "Created {}. Label tasks that depend on it with {}",
+        decision.id().unwrap(),
+        decision.label().unwrap()
+    );
+    Ok(())
+}
+
+async fn list(dv: &mut System, args: ListArgs) -> Result<()> {
+    let where_clause = match args.status {
+        Some(status) => format!("status = '{status}'"),
+        None => "1 = 1".to_owned(),
+    };
+    let mut decisions = dv.query::<Decision>(&where_clause).await?;
+    decisions.sort_by_key(|d| (d.status, d.id()));
+    for decision in decisions {
+        let tasks = linked_tasks(dv, &decision, None).await?;
+        let open = linked_tasks(dv, &decision, Some(StatusFilter::Open)).await?;
+        println!(
+            "{} {:<10} {:>3}/{:<3} {}",
+            format!("{:<32}", decision.id().unwrap()).bold(),
+            decision.status,
+            open.len(),
+            tasks.len(),
+            decision.title
+        );
+    }
+    Ok(())
+}
+
+async fn show(dv: &mut System, args: ShowArgs) -> Result<()> {
+    let decision = dv.read_doc::<Decision, _>(find(dv, &args.id)?)?;
+    println!(
+        "{}: {}",
+        decision.id().unwrap().bold(),
+        decision.title.bold().green()
+    );
+    println!("Status: {}", decision.status.bold());
+    if let Some(owner) = &decision.owner {
+        println!("Owner: {owner}");
+    }
+    if let Some(decided) = decision.decided {
+        println!("Decided: {decided}");
+    }
+    if let Some(by) = &decision.superseded_by {
+        println!("Superseded by: {by}");
+    }
+    if !decision.options.is_empty() {
+        println!("Options:");
+        for option in &decision.options {
+            match decision.outcome.as_ref() == Some(option) {
+                true => println!("  * {}", option.bold()),
+                false => println!("  - {option}"),
+            }
+        }
+    }
+    if let Some(outcome) = decision
+        .outcome
+        .as_ref()
+        .filter(|o| !decision.options.contains(o))
+    {
+        println!("Outcome: {}", outcome.bold());
+    }
+    if let Some(context) = &decision.context {
+        println!("\n{}", MadSkin::default().term_text(context));
+    }
+
+    let tasks = linked_tasks(dv, &decision, None).await?;
+    if !tasks.is_empty() {
+        println!("\nTasks ({}):", decision.label().unwrap());
+        for task in tasks {
+            let blocked = match decision.status {
+                DecisionStatus::Proposed if is_open(&task) => " (blocked)",
+                _ => "",
+            };
+            println!(
+                "  {} {} [{}]{}",
+                task.id().unwrap(),
+                task.title,
+                task.status.as_deref().unwrap_or("Todo"),
+                blocked.red()
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn accept(dv: &mut System, args: AcceptArgs) -> Result<()> {
+    let path = find(dv, &args.id)?;
+    let mut decision = dv.read_doc::<Decision, _>(&path)?;
+    if decision.status != DecisionStatus::Proposed {
+        bail!("{} is already {}", args.id, decision.status);
+    }
+    decision.accept(args.outcome, Local::now().date_naive());
+    dv.update_doc(&path, decision.clone()).await?;
+    println!("Accepted {}", args.id);
+
+    let unblocked = linked_tasks(dv, &decision, Some(StatusFilter::Open)).await?;
+    if !unblocked.is_empty() {
+        println!("Unblocked:");
+    }
+    for task in unblocked {
+        println!(
+            "  {} {} ({})",
+            task.id().unwrap(),
+            task.title,
+            task.assignee.as_deref().unwrap_or("unassigned")
+        );
+    }
+    Ok(())
+}
+
+async fn supersede(dv: &mut System, args: SupersedeArgs) -> Result<()> {
+    let path = find(dv, &args.id)?;
+    find(dv, &args.by)?;
+    let mut decision = dv.read_doc::<Decision, _>(&path)?;
+    decision.status = DecisionStatus::Superseded;
+    decision.superseded_by = Some(args.by.clone());
+    dv.update_doc(&path, decision).await?;
+    println!("{} is superseded by {}", args.id, args.by);
+    Ok(())
+}
+
+fn find(dv: &System, id: &str) -> Result<std::path::PathBuf> {
+    match dv.find_doc::<Decision>(id) {
+        Some(path) => Ok(path),
+        None => bail!("Decision {} not found", id),
+    }
+}
+
+fn is_open(task: &Task) -> bool {
+    matches!(report::status(task), Status::Todo | Status::InProgress)
+}
+
+/// Tasks labeled with the decision, optionally only those in a status
+async fn linked_tasks(
+    dv: &System,
+    decision: &Decision,
+    status: Option<StatusFilter>,
+) -> Result<Vec<Task>> {
+    let filters = FilterArgs {
+        status,
+        labels: Labels(decision.label().into_iter().collect()),
+        ..FilterArgs::default()
+    };
+    Ok(dv.query::<Task>(&filters.to_where_clause()).await?)
+}
@@ -0,0 +1,362 @@
+use crate::{config, Status};
+use anyhow::{bail, Result};
+use clap::Args;
+use divvee::collection::{Collection, KINDS};
+use divvee::format::DocFormat;
+use divvee::task::Task;
+use divvee::{is_template, Error, RepoDoc, System};
+use owo_colors::OwoColorize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args, Debug)]
+pub struct DoctorCmd {
+    /// Only check this team
+    #[arg(long, short = 't')]
+    team: Option<String>,
+    /// Apply safe repairs: track untracked docs, add missing front matter,
+    /// normalize statuses and bring the index up to date
+    #[arg(long)]
+    fix: bool,
+}
+
+struct Problem {
+    path: PathBuf,
+    message: String,
+    fixed: bool,
+}
+
+/// Problems found so far, printed as they're found
+#[derive(Default)]
+struct Report {
+    problems: Vec<Problem>,
+}
+
+impl Report {
+    fn add(&mut self, path: &Path, message: impl Into<String>, fixed: bool) {
+        let problem = Problem {
+            path: path.to_owned(),
+            message: message.into(),
+            fixed,
+        };
+        match problem.fixed {
+            true => println!(
+                "{}: {} {}",
+                problem.path.display().bold(),
+                problem.message,
+                "(fixed)".green()
+            ),
+            false => println!("{}: {}", problem.path.display().bold(), problem.message),
+        }
+        self.problems.push(problem);
+    }
+
+    /// Adds a problem that `--fix` knows how to repair, repairing it if asked
+    fn repair(&mut self, path: &Path, message: impl Into<String>, fix: Option<Result<()>>) {
+        match fix {
+            None => self.add(path, message, false),
+            Some(Ok(())) => self.add(path, message, true),
+            Some(Err(err)) => self.add(
+                path,
+                format!("{} (fix failed: {err})", message.into()),
+                false,
+            ),
+        }
+    }
+}
+
+/// Checks every doc in the repo, continuing past bad files
+pub async fn run(dv: &mut System, args: DoctorCmd) -> Result<()> {
+    let teams = match &args.team {
+        Some(team) => vec![team.clone()],
+        None => dv.teams()?,
+    };
+    let mut report = Report::default();
+    let mut tasks: BTreeMap<PathBuf, Task> = BTreeMap::new();
+    // IDs by (team, number), so `DIV-01.md` and `div-1.md` collide with `DIV-1.md`
+    let mut ids: BTreeMap<(String, u32), PathBuf> = BTreeMap::new();
+
+    for team in &teams {
+        let dir = Task::KIND.dir(team);
+        let read_dir = match fs::read_dir(dv.root().join(&dir)) {
+            Ok(read_dir) => read_dir,
+            Err(err) => {
+                report.add(&dir, format!("could not be read ({err})"), false);
+                continue;
+            }
+        };
+        let mut entries: Vec<PathBuf> = read_dir
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .map(|e| dir.join(e.file_name()))
+            .filter(|path| !is_template(path))
+            .collect();
+        // canonical names first, so they keep their ID when another file duplicates it
+        entries.sort_by_key(|path| {
+            let canonical = parse_task_file(path)
+                .is_some_and(|(t, n)| path.file_stem().is_some_and(|s| *s == *format!("{t}-{n}")));
+            (!canonical, path.clone())
+        });
+
+        for path in entries {
+            let Some((file_team, num)) = parse_task_file(&path) else {
+                report.add(
+                    &path,
+                    "is not named like a task (TEAM-N.md or TEAM-N.kdl)",
+                    false,
+                );
+                continue;
+            };
+            if file_team != *team {
+                report.add(&path, format!("belongs to team {file_team}"), false);
+            }
+            let key = (file_team.to_uppercase(), num);
+            match ids.get(&key) {
+                Some(other) => {
+                    report.add(
+                        &path,
+                        format!("duplicates the ID of {}", other.display()),
+                        false,
+                    );
+                    continue;
+                }
+                None => ids.insert(key, path.clone()),
+            };
+
+            if !dv.is_tracked(&path)? {
+                let fix = args.fix.then(|| dv.track(&path).map_err(Into::into));
+                report.repair(&path, "is not tracked", fix);
+            }
+
+            let s = fs::read_to_string(dv.root().join(&path))?;
+            let task = match Task::parse_doc(&s, Some(path.clone())) {
+                Ok(task) => task,
+                Err(_) if is_bare_markdown(&path, &s) => {
+                    let fix = match args.fix {
+                        true => Some(add_front_matter(dv, &path, &s).await),
+                        false => None,
+                    };
+                    report.repair(&path, "has no front matter", fix);
+                    continue;
+                }
+                Err(Error::Parse(mut err)) => {
+                    // the path is already shown
+                    err.path = None;
+                    report.add(&path, format!("can't be parsed: {err}"), false);
+                    continue;
+                }
+                Err(err) => {
+                    report.add(&path, format!("can't be parsed: {err}"), false);
+                    continue;
+                }
+            };
+            if let Err(Error::Invalid { violations, .. }) = task.check(&s, &path, dv.config()) {
+                for violation in violations {
+                    report.add(&path, violation.to_string(), false);
+                }
+            }
+            tasks.insert(path, task);
+        }
+    }
+
+    // other kinds only need to parse and fit the repo config
+    let mut other_docs = 0;
+    for team in &teams {
+        for kind in KINDS.iter().filter(|kind| **kind != Task::KIND) {
+            let paths = match dv.collection_paths(kind, team) {
+                Ok(paths) => paths,
+                Err(err) => {
+                    report.add(&kind.dir(team), format!("could not be read ({err})"), false);
+                    continue;
+                }
+            };
+            for path in paths {
+                other_docs += 1;
+                let s = match fs::read_to_string(dv.root().join(&path)) {
+                    Ok(s) => s,
+                    Err(err) => {
+                        report.add(&path, format!("could not be read ({err})"), false);
+                        continue;
+                    }
+                };
+                match (kind.validate)(&s, &path, dv.config()) {
+                    Ok(()) => {}
+                    Err(Error::Invalid { violations, .. }) => {
+                        for violation in violations {
+                            report.add(&path, violation.to_string(), false);
+                        }
+                    }
+                    Err(Error::Parse(mut err)) => {
+                        err.path = None;
+                        report.add(&path, format!("can't be parsed: {err}"), false);
+                    }
+                    Err(err) => report.add(&path, format!("can't be parsed: {err}"), false),
+                }
+            }
+        }
+    }
+
+    let mut known_users: BTreeSet<String> = dv
+        .log()?
+        .into_iter()
+        .flat_map(|change| change.authors)
+        .collect();
+    known_users.insert(config::me().email.clone());
+
+    let team_set: BTreeSet<&str> = teams.iter().map(String::as_str).collect();
+    let existing: BTreeSet<String> = tasks.values().filter_map(|t| t.id()).collect();
+    for (path, task) in &mut tasks {
+        if let Some(status) = &task.status {
+            if status.parse::<Status>().is_err() {
+                let message = format!("has unknown status {status:?}");
+                match normalize_status(status) {
+                    Some(normal) => {
+                        let fix = match args.fix {
+                            true => {
+                                task.status = Some(normal.to_string());
+                                let fixed = dv.update_doc(&path, task.clone()).await;
+                                Some(fixed.map(|_| ()).map_err(Into::into))
+                            }
+                            false => None,
+                        };
+                        report.repair(path, format!("{message}, expected \"{normal}\""), fix);
+                    }
+                    None => report.add(path, message, false),
+                }
+            }
+        }
+        if let Some(assignee) = &task.assignee {
+            if !known_users.contains(assignee) {
+                report.add(
+                    path,
+                    format!("is assigned to {assignee}, who has never recorded a change"),
+                    false,
+                );
+            }
+        }
+        for (name, id) in relations(task, &team_set) {
+            if !existing.contains(&id) {
+                report.add(
+                    path,
+                    format!("props.{name} refers to missing task {id}"),
+                    false,
+                );
+            }
+        }
+    }
+
+    let indexed: BTreeSet<String> = dv
+        .query::<Task>("1 = 1")
+        .await?
+        .into_iter()
+        .filter_map(|t| t.id())
+        .filter(|id| {
+            id.split_once('-')
+                .is_some_and(|(team, _)| team_set.contains(team))
+        })
+        .collect();
+    for (path, task) in &tasks {
+        if task.id().is_some_and(|id| !indexed.contains(&id)) {
+            let fix = match args.fix {
+                true => Some(dv.reindex::<Task>(path).await.map_err(Into::into)),
+                false => None,
+            };
+            report.repair(path, "is missing from the index", fix);
+        }
+    }
+    for id in indexed.difference(&existing) {
+        let path = crate::util::task_path(dv, id)?;
+        if dv.root().join(&path).exists() {
+            // the file exists but couldn't be checked, which was reported above
+            continue;
+        }
+        let fix = match args.fix {
+            true => Some(
+                dv.reindex_paths::<Task>(std::slice::from_ref(&path))
+                    .await
+                    .map_err(Into::into),
+            ),
+            false => None,
+        };
+        report.repair(&path, "is indexed but doesn't exist", fix);
+    }
+
+    let total = report.problems.len();
+    let fixed = report.problems.iter().filter(|p| p.fixed).count();
+    match (total, fixed) {
+        (0, _) => println!("No problems found in {} docs", tasks.len() + other_docs),
+        (total, fixed) if total == fixed => println!("Fixed {total} problems"),
+        (total, 0) => bail!("Found {} problems", total),
+        (total, fixed) => bail!("Found {} problems, fixed {}", total, fixed),
+    }
+    Ok(())
+}
+
+/// Team and number from a path like `DIV/tasks/DIV-12.md`
+fn parse_task_file(path: &Path) -> Option<(String, u32)> {
+    let ext = path.extension()?;
+    if !DocFormat::ALL.iter().any(|f| ext == f.extension()) {
+        return None;
+    }
+    let stem = path.file_stem()?.to_string_lossy();
+    let (team, num) = stem.split_once('-')?;
+    Some((team.to_owned(), num.parse().ok()?))
+}
+
+/// Canonical status for near misses like `in-progress` or `DONE`
+fn normalize_status(status: &str) -> Option<Status> {
+    let key = |s: &str| {
+        s.chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<String>()
+            .to_lowercase()
+    };
+    Status::ALL
+        .into_iter()
+        .find(|s| key(&s.to_string()) == key(status))
+}
+
+/// Prop values that look like IDs of tasks in a known team, with the prop they're in
+fn relations(task: &Task, teams: &BTreeSet<&str>) -> Vec<(String, String)> {
+    let is_id = |s: &str| {
+        s.split_once('-').is_some_and(|(team, num)| {
+            teams.contains(team) && !num.is_empty() && num.chars().all(|c| c.is_ascii_digit())
+        })
+    };
+    let mut relations = Vec::new();
+    for (name, value) in &task.props {
+        let values = match value {
+            serde_yaml::Value::Sequence(items) => items.iter().collect(),
+            value => vec![value],
+        };
+        for s in values.into_iter().filter_map(|v| v.as_str()) {
+            if is_id(s) {
+                relations.push((name.clone(), s.to_owned()));
+            }
+        }
+    }
+    relations
+}
+
+/// Whether a doc is markdown without any front matter
+fn is_bare_markdown(path: &Path, s: &str) -> bool {
+    DocFormat::detect(s, Some(path)) == DocFormat::Yaml && !s.trim_start().starts_with("---")
+}
+
+/// Turns a bare markdown file into a task, using its first line as the title
+async fn add_front_matter(dv: &System, path: &Path, s: &str) -> Result<()> {
+    let mut lines = s.lines().skip_while(|l| l.trim().is_empty());
+    let title = lines
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches('#')
+        .trim();
+    let mut task = Task::new(title);
+    let description = lines.collect::<Vec<_>>().join("\n").trim().to_owned();
+    if !description.is_empty() {
+        task.description = Some(description);
+    }
+    dv.update_doc(path, task).await?;
+    Ok(())
+}
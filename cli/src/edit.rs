@@ -1,11 +1,10 @@
 use crate::{config, util, Status};
 use anyhow::Result;
 use clap::Args;
-use divvee::task::Task;
+use divvee::collection::Collection;
+use divvee::task::{Priority, Task};
 use divvee::*;
 use log::debug;
-use std::path::Path;
-use tempfile::Builder;
 
 #[derive(Args, Debug)]
 pub struct EditCmd {
@@ -14,6 +13,18 @@ pub struct EditCmd {
     assignee: Option<String>,
     #[arg(long, short = 's', value_enum)]
     status: Option<Status>,
+    /// Set priority (none, low, medium, high, urgent)
+    #[arg(long, short = 'p')]
+    priority: Option<Priority>,
+    /// Set the task type (e.g. bug), or clear it with ""
+    #[arg(long = "type")]
+    kind: Option<String>,
+    /// Set the start date (YYYY-MM-DD), or clear it with ""
+    #[arg(long)]
+    start: Option<String>,
+    /// Set the due date (YYYY-MM-DD), or clear it with ""
+    #[arg(long)]
+    due: Option<String>,
     #[arg(long, short = 'i')]
     interactive: bool,
     // #[arg(long, short = 't')]
@@ -28,11 +39,10 @@ pub struct EditCmd {
 pub async fn run(dv: &mut System, mut args: EditCmd) -> Result<()> {
     let (team, id) = util::team_and_id(&args.id)?;
 
-    let rel_dir = Path::new(&team).join("tasks");
+    let rel_dir = Task::KIND.dir(&team);
     debug!("edit rel_dir: {}", rel_dir.display());
 
-    let fname = Path::new(&id).with_extension("md");
-    let doc = dv.load(rel_dir.join(fname))?;
+    let doc = dv.load(dv.doc_path(&rel_dir, &id))?;
     let mut task = doc.read_doc::<Task>()?;
     let original = task.clone();
 
@@ -59,6 +69,25 @@ pub async fn run(dv: &mut System, mut args: EditCmd) -> Result<()> {
         task.status = Some(status.to_string());
     }
 
+    if let Some(priority) = args.priority.take() {
+        task.priority = priority;
+    }
+
+    if let Some(kind) = args.kind.take() {
+        task.kind = match kind.is_empty() {
+            true => None,
+            false => Some(kind),
+        }
+    }
+
+    if let Some(start) = args.start.take() {
+        task.start = util::parse_date(&start)?;
+    }
+
+    if let Some(due) = args.due.take() {
+        task.due = util::parse_date(&due)?;
+    }
+
     // if let Some(team) = args.team.take() {
     //     TODO: move file
     //     lookup new dir
@@ -69,9 +98,21 @@ pub async fn run(dv: &mut System, mut args: EditCmd) -> Result<()> {
     // }
 
     if args.interactive {
-        let template = task.to_doc_string();
-        let editted = edit::edit_with_builder(template, Builder::new().suffix(".md"))?;
-        task = Task::parse_doc(&editted, None)?;
+        // edit the file as it is, so comments and unknown keys can be edited too
+        let raw = doc.read_to_string()?;
+        let template = task.update_doc_string(&raw);
+        let edited = util::edit_doc(template, |s| {
+            Task::parse_valid(s, doc.repo_path(), dv.config()).map(|_| s.to_owned())
+        })?;
+        match edited != raw {
+            true => {
+                dv.update_doc_string::<Task, _>(doc.repo_path(), &edited)
+                    .await?;
+                println!("Updated {}", id);
+            }
+            false => println!("No changes were made"),
+        }
+        return Ok(());
     }
 
     if task != original {
@@ -0,0 +1,250 @@
+use crate::print::TaskRow;
+use anyhow::{bail, Error, Result};
+use std::cmp::Ordering;
+use std::{fmt, str};
+
+/// A task attribute that can be displayed as a column or used as a sort key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Field {
+    Id,
+    Status,
+    Assignee,
+    Title,
+    Type,
+    Priority,
+    Rank,
+    Start,
+    Due,
+    Labels,
+    Created,
+    Updated,
+    Prop(String),
+}
+
+impl Field {
+    pub fn defaults() -> Vec<Field> {
+        vec![Field::Id, Field::Assignee, Field::Title]
+    }
+
+    /// Whether this field is only available by reading the full document
+    pub fn needs_doc(&self) -> bool {
+        matches!(self, Field::Labels | Field::Prop(_)) || self.needs_meta()
+    }
+
+    /// Whether this field is only available from the document history
+    pub fn needs_meta(&self) -> bool {
+        matches!(self, Field::Created | Field::Updated)
+    }
+
+    pub fn header(&self) -> &str {
+        match self {
+            Field::Id => "ID",
+            Field::Status => "Status",
+            Field::Assignee => "Assignee",
+            Field::Title => "Title",
+            Field::Type => "Type",
+            Field::Priority => "Priority",
+            Field::Rank => "Rank",
+            Field::Start => "Start",
+            Field::Due => "Due",
+            Field::Labels => "Labels",
+            Field::Created => "Created",
+            Field::Updated => "Updated",
+            Field::Prop(name) => name,
+        }
+    }
+
+    pub fn value(&self, row: &TaskRow) -> String {
+        let task = &row.task;
+        match self {
+            Field::Id => task.id().unwrap_or_default(),
+            Field::Status => task.status.clone().unwrap_or_default(),
+            Field::Assignee => task.assignee.clone().unwrap_or_default(),
+            Field::Title => task.title.clone(),
+            Field::Type => task.kind.clone().unwrap_or_default(),
+            Field::Priority => match task.priority.is_none() {
+                true => String::new(),
+                false => task.priority.to_string(),
+            },
+            Field::Rank => task.rank.map(|r| r.to_string()).unwrap_or_default(),
+            Field::Start => task.start.map(|d| d.to_string()).unwrap_or_default(),
+            Field::Due => row.due().map(|d| d.to_string()).unwrap_or_default(),
+            Field::Labels => task.labels.join(" "),
+            Field::Created => row
+                .created()
+                .map(|c| c.timestamp.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            Field::Updated => row
+                .updated()
+                .map(|c| c.timestamp.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            Field::Prop(name) => match task.props.get(name).map(serde_json::to_value) {
+                Some(Ok(serde_json::Value::String(s))) => s,
+                Some(Ok(val)) => val.to_string(),
+                _ => String::new(),
+            },
+        }
+    }
+
+    /// Structured value of this field for machine-readable views
+    pub fn json_value(&self, row: &TaskRow) -> serde_json::Value {
+        use serde_json::{json, Value};
+        let task = &row.task;
+        match self {
+            Field::Id => json!(task.id()),
+            Field::Status => json!(task.status),
+            Field::Assignee => json!(task.assignee),
+            Field::Title => json!(task.title),
+            Field::Type => json!(task.kind),
+            Field::Priority => json!(task.priority),
+            Field::Rank => json!(task.rank),
+            Field::Start => json!(task.start),
+            Field::Due => json!(row.due()),
+            Field::Labels => json!(task.labels),
+            Field::Created => json!(row.created()),
+            Field::Updated => json!(row.updated()),
+            Field::Prop(name) => task
+                .props
+                .get(name)
+                .and_then(|val| serde_json::to_value(val).ok())
+                .unwrap_or(Value::Null),
+        }
+    }
+
+    /// Compares two rows by this field. Missing values sort last.
+    pub fn compare(&self, a: &TaskRow, b: &TaskRow) -> Ordering {
+        match self {
+            Field::Id => id_key(a).cmp(&id_key(b)),
+            Field::Status => a.status().cmp(&b.status()),
+            // Most urgent first, then by backlog rank
+            Field::Priority => b
+                .task
+                .priority
+                .cmp(&a.task.priority)
+                .then_with(|| cmp_some_first(a.task.rank, b.task.rank)),
+            Field::Rank => cmp_some_first(a.task.rank, b.task.rank),
+            Field::Start => cmp_some_first(a.task.start, b.task.start),
+            Field::Due => cmp_some_first(a.due(), b.due()),
+            Field::Created => cmp_some_first(
+                a.created().map(|c| c.timestamp),
+                b.created().map(|c| c.timestamp),
+            ),
+            Field::Updated => cmp_some_first(
+                a.updated().map(|c| c.timestamp),
+                b.updated().map(|c| c.timestamp),
+            ),
+            _ => {
+                let (a, b) = (self.value(a), self.value(b));
+                match (a.parse::<f64>(), b.parse::<f64>()) {
+                    (Ok(a), Ok(b)) => a.total_cmp(&b),
+                    _ => cmp_some_first(non_empty(a), non_empty(b)),
+                }
+            }
+        }
+    }
+}
+
+fn id_key(row: &TaskRow) -> (String, u32) {
+    let id = row.task.id().unwrap_or_default();
+    match id.split_once('-') {
+        Some((team, num)) => (team.to_owned(), num.parse().unwrap_or(0)),
+        None => (id, 0),
+    }
+}
+
+fn non_empty(s: String) -> Option<String> {
+    (!s.is_empty()).then_some(s)
+}
+
+fn cmp_some_first<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+impl str::FromStr for Field {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Field> {
+        let field = match s.to_lowercase().as_str() {
+            "id" => Field::Id,
+            "status" => Field::Status,
+            "assignee" => Field::Assignee,
+            "title" => Field::Title,
+            "type" => Field::Type,
+            "priority" => Field::Priority,
+            "rank" => Field::Rank,
+            "start" => Field::Start,
+            "due" => Field::Due,
+            "labels" => Field::Labels,
+            "created" => Field::Created,
+            "updated" => Field::Updated,
+            _ => match s.strip_prefix("props.") {
+                Some(name) if !name.is_empty() => Field::Prop(name.to_owned()),
+                _ => bail!("Unknown field {}", s),
+            },
+        };
+        Ok(field)
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Field::Prop(name) => write!(f, "props.{name}"),
+            _ => write!(f, "{}", self.header().to_lowercase()),
+        }
+    }
+}
+
+/// How to group tasks in list output
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    Status,
+    Assignee,
+    /// Label prefix, e.g. `s` groups by sprint labels such as `s-alpha`
+    Label(String),
+}
+
+impl GroupBy {
+    pub fn needs_doc(&self) -> bool {
+        matches!(self, GroupBy::Label(_))
+    }
+
+    /// Returns the group key for a row, or None if it doesn't belong to any group
+    pub fn key(&self, row: &TaskRow) -> Option<String> {
+        match self {
+            GroupBy::Status => Some(row.status().to_string()),
+            GroupBy::Assignee => row.task.assignee.clone(),
+            GroupBy::Label(prefix) => row.task.labels.iter().find_map(|label| {
+                let (p, rest) = label.split_once('-')?;
+                p.eq_ignore_ascii_case(prefix).then(|| rest.to_owned())
+            }),
+        }
+    }
+
+    /// Orders groups, keeping the ungrouped tasks last
+    pub fn compare(&self, a: &TaskRow, b: &TaskRow) -> Ordering {
+        match self {
+            GroupBy::Status => a.status().cmp(&b.status()),
+            _ => cmp_some_first(self.key(a), self.key(b)),
+        }
+    }
+}
+
+impl str::FromStr for GroupBy {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<GroupBy> {
+        match s.to_lowercase().as_str() {
+            "status" => Ok(GroupBy::Status),
+            "assignee" => Ok(GroupBy::Assignee),
+            prefix => match prefix.trim_end_matches('-') {
+                "" => bail!("Expected status, assignee, or a label prefix"),
+                p if p.contains('-') => bail!("Invalid label prefix {}", s),
+                p => Ok(GroupBy::Label(p.to_owned())),
+            },
+        }
+    }
+}
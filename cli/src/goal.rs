@@ -0,0 +1,497 @@
+use crate::chart;
+use crate::report::{self, OutputArgs, Table};
+use crate::{config, util, Status};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use clap::{Args, Subcommand};
+use divvee::collection::Collection;
+use divvee::goal::{Goal, GoalStatus, KeyResult};
+use divvee::task::Task;
+use divvee::{RepoDoc, System};
+use log::debug;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Prefix of the labels that group tasks into epics, e.g. `e-onboarding`
+pub const EPIC_PREFIX: &str = "e-";
+
+#[derive(Args, Debug)]
+pub struct GoalCmd {
+    #[command(subcommand)]
+    action: GoalAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum GoalAction {
+    /// Set a goal. Tasks labeled `g-<id>` are aligned with it
+    New(NewArgs),
+    /// List goals with the progress of their key results
+    List,
+    /// Show a goal, its key results and the work aligned with it
+    Show(ShowArgs),
+    /// Align tasks or whole epics with a goal
+    Align(AlignArgs),
+    /// Record the current value of a key result
+    Progress(ProgressArgs),
+}
+
+#[derive(Args, Debug)]
+struct NewArgs {
+    title: String,
+    #[arg(long, short = 't')]
+    team: Option<String>,
+    /// Opens the editor if not given
+    #[arg(long, short = 'd')]
+    description: Option<String>,
+    /// A key result with its target. Can be given more than once
+    #[arg(long = "kr", value_name = "NAME=TARGET")]
+    key_results: Vec<KeyResult>,
+    /// When the goal is meant to be met (e.g. 2026-Q4)
+    #[arg(long)]
+    period: Option<String>,
+    /// Who owns the goal. Defaults to you
+    #[arg(long)]
+    owner: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct ShowArgs {
+    id: String,
+}
+
+#[derive(Args, Debug)]
+#[group(id = "work", required = true, multiple = true)]
+struct AlignArgs {
+    goal: String,
+    /// Tasks to label with the goal
+    #[arg(group = "work")]
+    tasks: Vec<String>,
+    /// Epic to align as a whole (e.g. onboarding or e-onboarding). Can be given more than once
+    #[arg(long = "epic", group = "work")]
+    epics: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct ProgressArgs {
+    goal: String,
+    /// Name of the key result
+    key_result: String,
+    current: f64,
+}
+
+#[derive(Args, Debug)]
+pub struct GoalReportArgs {
+    /// Include achieved and dropped goals
+    #[arg(long)]
+    all: bool,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub async fn run(dv: &mut System, args: GoalCmd) -> Result<()> {
+    match args.action {
+        GoalAction::New(args) => new(dv, args).await,
+        GoalAction::List => list(dv),
+        GoalAction::Show(args) => show(dv, args).await,
+        GoalAction::Align(args) => align(dv, args).await,
+        GoalAction::Progress(args) => progress(dv, args).await,
+    }
+}
+
+async fn new(dv: &mut System, args: NewArgs) -> Result<()> {
+    let team = match args.team {
+        Some(team) => team,
+        None => config::default_team()?,
+    };
+    let rel_dir = Goal::KIND.dir(&team);
+    let mut goal = Goal::new(&args.title);
+    goal.key_results = args.key_results;
+    goal.period = args.period;
+    goal.owner = Some(args.owner.unwrap_or_else(|| config::me().email.clone()));
+    match args.description {
+        Some(description) => goal.description = Some(description).filter(|d| !d.is_empty()),
+        None => {
+            let template = goal.to_doc_string_as(dv.config().doc_format(&rel_dir));
+            goal = util::edit_doc(template, |s| Goal::parse_doc(s, None))?;
+        }
+    }
+
+    let id = dv.next_doc_id::<Goal>(&team, &goal.title)?;
+    let path = dv.doc_path(&rel_dir, &id);
+    debug!("Creating goal at {}", path.display());
+    let goal = dv.create_doc(path, goal).await?.read_doc::<Goal>()?;
+    println!(
+        "Created {}. Align tasks with it using {}",
+        goal.id().unwrap(),
+        goal.label().unwrap()
+    );
+    Ok(())
+}
+
+fn list(dv: &mut System) -> Result<()> {
+    let mut goals = read_goals(dv)?;
+    goals.sort_by_key(|g| (g.status, g.period.clone(), g.id()));
+    for goal in goals {
+        println!(
+            "{} {:<9} {:<8} {:>4} {}",
+            format!("{:<32}", goal.id().unwrap()).bold(),
+            goal.status,
+            goal.period.as_deref().unwrap_or(""),
+            goal.progress().map(percent).unwrap_or_default(),
+            goal.title
+        );
+    }
+    Ok(())
+}
+
+async fn show(dv: &mut System, args: ShowArgs) -> Result<()> {
+    let goal = dv.read_doc::<Goal, _>(find(dv, &args.id)?)?;
+    println!(
+        "{}: {}",
+        goal.id().unwrap().bold(),
+        goal.title.bold().green()
+    );
+    println!("Status: {}", goal.status.bold());
+    if let Some(owner) = &goal.owner {
+        println!("Owner: {owner}");
+    }
+    if let Some(period) = &goal.period {
+        println!("Period: {period}");
+    }
+    if let Some(description) = &goal.description {
+        println!("\n{}", termimad::MadSkin::default().term_text(description));
+    }
+    let labels = aligned_labels(&goal);
+    let rollup = GoalRollup::new(&goal, aligned_tasks(dv, &labels).await?);
+    println!();
+    for (title, table) in rollup.tables() {
+        println!("{title}\n\n{}", table.to_text());
+    }
+    Ok(())
+}
+
+async fn align(dv: &mut System, args: AlignArgs) -> Result<()> {
+    let path = find(dv, &args.goal)?;
+    let mut goal = dv.read_doc::<Goal, _>(&path)?;
+    let label = goal.label().unwrap();
+    for id in &args.tasks {
+        let path = util::task_path(dv, id)?;
+        let mut task = match dv.read_doc::<Task, _>(&path) {
+            Ok(task) => task,
+            Err(divvee::Error::IoError(_)) => bail!("{} not found", id),
+            Err(err) => return Err(err.into()),
+        };
+        if task.labels.contains(&label) {
+            println!("{id} is already aligned with {}", args.goal);
+            continue;
+        }
+        task.labels.push(label.clone());
+        dv.update_doc(&path, task).await?;
+        println!("Aligned {id} with {}", args.goal);
+    }
+
+    let epics: Vec<String> = args
+        .epics
+        .iter()
+        .map(|epic| match epic.starts_with(EPIC_PREFIX) {
+            true => epic.clone(),
+            false => format!("{EPIC_PREFIX}{epic}"),
+        })
+        .filter(|epic| !goal.epics.contains(epic))
+        .collect();
+    if !epics.is_empty() {
+        goal.epics.extend(epics.iter().cloned());
+        dv.update_doc(&path, goal).await?;
+        println!("Aligned {} with {}", epics.join(", "), args.goal);
+    }
+    Ok(())
+}
+
+async fn progress(dv: &mut System, args: ProgressArgs) -> Result<()> {
+    let path = find(dv, &args.goal)?;
+    let mut goal = dv.read_doc::<Goal, _>(&path)?;
+    let Some(kr) = goal.key_result_mut(&args.key_result) else {
+        bail!("{} has no key result {:?}", args.goal, args.key_result);
+    };
+    kr.current = args.current;
+    let line = format!(
+        "{}: {}/{} ({})",
+        kr.name,
+        kr.current,
+        kr.target,
+        percent(kr.progress())
+    );
+    dv.update_doc(&path, goal).await?;
+    println!("{line}");
+    Ok(())
+}
+
+fn find(dv: &System, id: &str) -> Result<PathBuf> {
+    match dv.find_doc::<Goal>(id) {
+        Some(path) => Ok(path),
+        None => bail!("Goal {} not found", id),
+    }
+}
+
+/// Goals of every team
+fn read_goals(dv: &System) -> Result<Vec<Goal>> {
+    let mut goals = Vec::new();
+    for team in dv.teams()? {
+        goals.extend(dv.read_docs::<Goal>(&team)?);
+    }
+    Ok(goals)
+}
+
+/// Labels that align work with a goal: its own and those of its epics
+fn aligned_labels(goal: &Goal) -> Vec<String> {
+    goal.label().into_iter().chain(goal.epics.clone()).collect()
+}
+
+fn in_labels(labels: &[String]) -> String {
+    let quoted: Vec<String> = labels.iter().map(|l| util::sql_str(l)).collect();
+    format!(
+        "id IN (SELECT task_id FROM task_labels WHERE label IN ({}))",
+        quoted.join(", ")
+    )
+}
+
+async fn aligned_tasks(dv: &System, labels: &[String]) -> Result<Vec<Task>> {
+    Ok(dv.query::<Task>(&in_labels(labels)).await?)
+}
+
+fn percent(share: f64) -> String {
+    format!("{:.0}%", share * 100.0)
+}
+
+/// Where effort goes: work aligned with each goal, and in-progress work aligned with none
+#[derive(Serialize)]
+struct Rollup {
+    generated: DateTime<Utc>,
+    goals: Vec<GoalRollup>,
+    unaligned_in_progress: Vec<TaskRef>,
+}
+
+#[derive(Serialize)]
+struct GoalRollup {
+    id: String,
+    title: String,
+    status: GoalStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    period: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    key_results: Vec<KeyResult>,
+    /// Mean progress of the key results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    progress: Option<f64>,
+    work: Work,
+    /// Aligned work per epic. Tasks without an epic are under `(no epic)`
+    epics: BTreeMap<String, Work>,
+}
+
+/// Task counts, leaving out canceled and duplicate tasks
+#[derive(Serialize, Default, Clone, Copy)]
+struct Work {
+    total: usize,
+    todo: usize,
+    in_progress: usize,
+    done: usize,
+}
+
+#[derive(Serialize)]
+struct TaskRef {
+    id: String,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignee: Option<String>,
+}
+
+impl Work {
+    fn add(&mut self, task: &Task) {
+        match report::status(task) {
+            Status::Todo => self.todo += 1,
+            Status::InProgress => self.in_progress += 1,
+            Status::Done => self.done += 1,
+            Status::Canceled | Status::Duplicate => return,
+        }
+        self.total += 1;
+    }
+
+    fn complete(&self) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => self.done as f64 / total as f64,
+        }
+    }
+
+    fn row(&self, name: &str) -> Vec<String> {
+        vec![
+            name.to_owned(),
+            self.total.to_string(),
+            self.done.to_string(),
+            self.in_progress.to_string(),
+            percent(self.complete()),
+        ]
+    }
+}
+
+impl GoalRollup {
+    fn new(goal: &Goal, tasks: Vec<Task>) -> GoalRollup {
+        let mut work = Work::default();
+        let mut epics: BTreeMap<String, Work> = BTreeMap::new();
+        for task in &tasks {
+            work.add(task);
+            let mut task_epics: Vec<&String> = task
+                .labels
+                .iter()
+                .filter(|l| l.starts_with(EPIC_PREFIX))
+                .collect();
+            let none = "(no epic)".to_owned();
+            if task_epics.is_empty() {
+                task_epics.push(&none);
+            }
+            for epic in task_epics {
+                epics.entry(epic.clone()).or_default().add(task);
+            }
+        }
+        GoalRollup {
+            id: goal.id().unwrap(),
+            title: goal.title.clone(),
+            status: goal.status,
+            period: goal.period.clone(),
+            owner: goal.owner.clone(),
+            key_results: goal.key_results.clone(),
+            progress: goal.progress(),
+            work,
+            epics,
+        }
+    }
+
+    fn tables(&self) -> Vec<(String, Table)> {
+        let mut sections = Vec::new();
+        if !self.key_results.is_empty() {
+            let mut table = Table::new(&["Key result", "Current", "Target", "Progress"]);
+            for kr in &self.key_results {
+                table.rows.push(vec![
+                    kr.name.clone(),
+                    kr.current.to_string(),
+                    kr.target.to_string(),
+                    percent(kr.progress()),
+                ]);
+            }
+            let progress = self.progress.map(percent).unwrap_or_default();
+            sections.push((format!("Key results ({progress})"), table));
+        }
+        let mut table = Table::new(&["Epic", "Tasks", "Done", "In progress", "Complete"]);
+        for (epic, work) in &self.epics {
+            table.rows.push(work.row(epic));
+        }
+        table.rows.push(self.work.row("Total"));
+        sections.push(("Aligned work".to_owned(), table));
+        sections
+    }
+}
+
+impl Rollup {
+    fn unaligned_table(&self) -> Table {
+        let mut table = Table::new(&["Task", "Assignee", "Title"]);
+        for task in &self.unaligned_in_progress {
+            table.rows.push(vec![
+                task.id.clone(),
+                task.assignee.clone().unwrap_or_default(),
+                task.title.clone(),
+            ]);
+        }
+        table
+    }
+
+    fn heading(goal: &GoalRollup) -> String {
+        let mut heading = format!("{}: {} ({}", goal.id, goal.title, goal.status);
+        if let Some(period) = &goal.period {
+            let _ = write!(heading, ", {period}");
+        }
+        heading.push(')');
+        heading
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        for goal in &self.goals {
+            let _ = writeln!(out, "{}\n", Rollup::heading(goal));
+            for (title, table) in goal.tables() {
+                let _ = writeln!(out, "{title}\n");
+                out.push_str(&table.to_text());
+                out.push('\n');
+            }
+        }
+        let _ = writeln!(
+            out,
+            "Unaligned work in progress ({})\n",
+            self.unaligned_in_progress.len()
+        );
+        out.push_str(&self.unaligned_table().to_text());
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let mut out = String::new();
+        for goal in &self.goals {
+            let _ = writeln!(out, "<h2>{}</h2>", chart::escape(&Rollup::heading(goal)));
+            for (title, table) in goal.tables() {
+                let _ = writeln!(out, "<h3>{}</h3>", chart::escape(&title));
+                out.push_str(&table.to_html());
+            }
+        }
+        let _ = writeln!(out, "<h2>Unaligned work in progress</h2>");
+        out.push_str(&self.unaligned_table().to_html());
+        out
+    }
+}
+
+/// Rollup of every goal, for `dv report goals`
+pub async fn report(dv: &System, args: &GoalReportArgs) -> Result<()> {
+    let mut goals = read_goals(dv)?;
+    goals.sort_by_key(|g| (g.status, g.period.clone(), g.id()));
+
+    let mut all_labels = Vec::new();
+    let mut rollups = Vec::new();
+    for goal in &goals {
+        let labels = aligned_labels(goal);
+        if goal.status != GoalStatus::Dropped {
+            all_labels.extend(labels.iter().cloned());
+        }
+        if goal.status == GoalStatus::Active || args.all {
+            rollups.push(GoalRollup::new(goal, aligned_tasks(dv, &labels).await?));
+        }
+    }
+
+    // labels of dropped or unknown goals don't count as aligned
+    let mut where_clause = "status = 'In Progress'".to_owned();
+    if !all_labels.is_empty() {
+        let _ = write!(where_clause, " AND NOT {}", in_labels(&all_labels));
+    }
+    let unaligned = dv
+        .query::<Task>(&where_clause)
+        .await?
+        .into_iter()
+        .map(|task| TaskRef {
+            id: task.id().unwrap(),
+            title: task.title,
+            assignee: task.assignee,
+        })
+        .collect();
+
+    let rollup = Rollup {
+        generated: Utc::now(),
+        goals: rollups,
+        unaligned_in_progress: unaligned,
+    };
+    args.output.write_report(
+        &rollup,
+        || rollup.to_text(),
+        || report::html_page("Goals", &rollup.to_html()),
+    )
+}
@@ -1,7 +1,14 @@
-use crate::{config, print::print_tasks, FilterArgs, StatusFilter, View};
+use crate::field::{Field, GroupBy};
+use crate::print::{print_tasks, TaskRow};
+use crate::{config, template, util, FilterArgs, StatusFilter, View};
 use anyhow::{bail, Result};
 use clap::Args;
+use divvee::collection::Collection;
+use divvee::repo::{AsOf, ChangeInfo};
+use divvee::task::Task;
 use divvee::*;
+use log::warn;
+use std::collections::{BTreeMap, HashSet};
 
 #[derive(Args, Debug)]
 pub struct ListCmd {
@@ -9,10 +16,66 @@ pub struct ListCmd {
     filters: FilterArgs,
     #[arg(long, short = 'v', default_value_t=View::Line)]
     view: View,
+    #[command(flatten)]
+    layout: LayoutArgs,
+    /// Template string, template file, or name of a template in the repo's templates/ dir
+    #[arg(long, short = 'f')]
+    format: Option<String>,
+    /// List tasks as they were at a past date (YYYY-MM-DD), time (RFC 3339) or change hash
+    #[arg(long, value_parser = util::parse_as_of)]
+    as_of: Option<AsOf>,
     // #[command(flatten)]
     // labels: Labels,
 }
 
+#[derive(Args, Debug, Default)]
+pub struct LayoutArgs {
+    /// Fields to sort by (e.g. status,priority,updated)
+    #[arg(long, value_delimiter = ',')]
+    sort: Vec<Field>,
+    /// Reverse the sort order
+    #[arg(long)]
+    reverse: bool,
+    /// Group by status, assignee, or a label prefix (e.g. s for sprints)
+    #[arg(long, short = 'g')]
+    group_by: Option<GroupBy>,
+    /// Columns to display: id, status, assignee, title, priority, rank, start, due, labels, created, updated, props.NAME
+    #[arg(long, short = 'c', value_delimiter = ',')]
+    columns: Vec<Field>,
+}
+
+impl LayoutArgs {
+    fn fields(&self) -> impl Iterator<Item = &Field> {
+        self.sort.iter().chain(&self.columns)
+    }
+
+    fn needs_doc(&self) -> bool {
+        self.fields().any(Field::needs_doc)
+            || self.group_by.as_ref().is_some_and(GroupBy::needs_doc)
+    }
+
+    fn needs_meta(&self) -> bool {
+        self.fields().any(Field::needs_meta)
+    }
+
+    /// Sorts rows by group first (if grouping), then by each sort field in turn
+    pub fn sort(&self, rows: &mut [TaskRow]) {
+        rows.sort_by(|a, b| {
+            let ord = self.sort.iter().fold(std::cmp::Ordering::Equal, |ord, f| {
+                ord.then_with(|| f.compare(a, b))
+            });
+            let ord = match self.reverse {
+                true => ord.reverse(),
+                false => ord,
+            };
+            match &self.group_by {
+                Some(group_by) => group_by.compare(a, b).then(ord),
+                None => ord,
+            }
+        });
+    }
+}
+
 impl ListCmd {
     pub fn mine() -> ListCmd {
         let assignee = config::me().email.clone();
@@ -24,26 +87,159 @@ impl ListCmd {
         ListCmd {
             filters,
             view: View::Line,
+            layout: LayoutArgs::default(),
+            format: None,
+            as_of: None,
         }
     }
 }
 
-pub async fn run(dv: &mut System, args: ListCmd) -> Result<()> {
+pub async fn run(dv: &mut System, mut args: ListCmd) -> Result<()> {
     // let team = match args.filters.team {
     //     Some(team) => Some(team),
     //     None => config::default_team().ok(),
     // };
 
-    let tasks = dv.query(&args.filters.to_where_clause()).await?;
+    args.filters.resolve_customers(dv.config());
+
+    let where_clause = args.filters.to_where_clause();
+    let tasks: Vec<Task> = match &args.as_of {
+        Some(at) => query_as_of(dv, at, args.filters.team.as_deref(), &where_clause).await?,
+        None => dv.query(&where_clause).await?,
+    };
     if tasks.is_empty() {
         bail!("No tasks matching query");
     }
 
-    if tasks.is_empty() {
+    // templates may use any field, so they always get the full document
+    let (needs_doc, needs_meta) = match args.format {
+        Some(_) => (true, true),
+        None => (args.layout.needs_doc(), args.layout.needs_meta()),
+    };
+    // past revisions are already full documents, and the current ones would be wrong
+    let needs_doc = needs_doc && args.as_of.is_none();
+    let mut rows = load_rows(dv, tasks, needs_doc, needs_meta)?;
+    if let (Some(at), true) = (&args.as_of, needs_meta) {
+        let mut meta = meta_as_of(dv, at, args.filters.team.as_deref())?;
+        for row in &mut rows {
+            row.meta = row.task.id().and_then(|id| meta.remove(&id));
+        }
+    }
+    args.layout.sort(&mut rows);
+
+    if let Some(format) = &args.format {
+        let source = template::resolve(dv.root(), format)?;
+        print!("{}", template::render_tasks(&source, &rows)?);
+    } else if rows.is_empty() {
         println!("No tasks found matching filter.")
     } else {
-        print_tasks(&tasks, args.view);
+        print_tasks(
+            &rows,
+            args.view,
+            &args.layout.columns,
+            args.layout.group_by.as_ref(),
+        );
     }
 
     Ok(())
 }
+
+/// Queries tasks as they were at a past point in the repo's history
+///
+/// Documents are rebuilt from history and indexed into a scratch DB so the usual
+/// filters apply; the full documents are returned in query order.
+async fn query_as_of(
+    dv: &System,
+    at: &AsOf,
+    team: Option<&str>,
+    where_clause: &str,
+) -> Result<Vec<Task>> {
+    let mut docs = BTreeMap::new();
+    for team in teams(dv, team)? {
+        let prefix = format!("{team}-");
+        let tasks: Vec<Task> = dv.read_dir_as_of(Task::KIND.dir(&team), at)?;
+        for mut task in tasks {
+            if let Some(id) = task.id().filter(|id| id.starts_with(&prefix)) {
+                // tasks deleted since have no current path to look up their creation from
+                let path = dv.doc_path(Task::KIND.dir(&team), &id);
+                if let Err(err) = dv.derive_fields(&path, &mut task) {
+                    warn!("No SLA due date for {id}: {err}");
+                }
+                docs.insert(id, task);
+            }
+        }
+    }
+    let records: Vec<Task> = docs.values().cloned().collect();
+    let matches: Vec<Task> = dv.query_records(&records, where_clause).await?;
+    Ok(matches
+        .into_iter()
+        .filter_map(|task| docs.remove(&task.id()?))
+        .collect())
+}
+
+/// When each task was created and last updated as of a past point, keyed by ID
+fn meta_as_of(
+    dv: &System,
+    at: &AsOf,
+    team: Option<&str>,
+) -> Result<BTreeMap<String, (ChangeInfo, Option<ChangeInfo>)>> {
+    // the log is newest first, so the point and every change after it in the log were applied
+    let log = dv.log()?;
+    let point = log.iter().position(|change| match at {
+        AsOf::Change(prefix) => change.hash.starts_with(prefix.as_str()),
+        AsOf::Time(time) => change.timestamp <= *time,
+    });
+    let applied: HashSet<&str> = log[point.unwrap_or(log.len())..]
+        .iter()
+        .map(|change| change.hash.as_str())
+        .collect();
+
+    let mut meta = BTreeMap::new();
+    for team in teams(dv, team)? {
+        for (path, revisions) in dv.history::<Task, _>(Task::KIND.dir(&team))? {
+            let Some(id) = path.file_stem().map(|s| s.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            let mut changes = revisions
+                .into_iter()
+                .map(|r| r.change)
+                .filter(|change| applied.contains(change.hash.as_str()));
+            if let Some(created) = changes.next() {
+                meta.insert(id, (created, changes.next_back()));
+            }
+        }
+    }
+    Ok(meta)
+}
+
+fn teams(dv: &System, team: Option<&str>) -> Result<Vec<String>> {
+    Ok(match team {
+        Some(team) => vec![team.to_owned()],
+        None => dv.teams()?,
+    })
+}
+
+/// Reloads tasks from their documents when fields that aren't indexed are needed
+fn load_rows(
+    dv: &System,
+    tasks: Vec<Task>,
+    needs_doc: bool,
+    needs_meta: bool,
+) -> Result<Vec<TaskRow>> {
+    if !needs_doc {
+        return Ok(tasks.into_iter().map(TaskRow::from).collect());
+    }
+    tasks
+        .iter()
+        .map(|task| {
+            let path = util::task_path(dv, &task.id().unwrap())?;
+            let mut row = match needs_meta {
+                true => TaskRow::from(dv.read_doc_with_meta::<Task, _>(path)?),
+                false => TaskRow::from(dv.read_doc::<Task, _>(path)?),
+            };
+            // derived fields are only in the index
+            row.task.sla_due = task.sla_due;
+            Ok(row)
+        })
+        .collect()
+}
@@ -1,26 +1,56 @@
 use anyhow::{bail, Result};
+use archive::ArchiveCmd;
+use chrono::{Datelike, Days, Local, NaiveDate};
 use clap::{arg, Arg, ArgMatches, Args, Command, FromArgMatches, Parser, Subcommand, ValueEnum};
 use config::Config;
 use create::CreateCmd;
+use decision::DecisionCmd;
 use derive_more::Display;
+use divvee::collection::ARCHIVE_PREFIX;
+use divvee::config::{RepoConfig, CUSTOMER_PREFIX};
+use divvee::task::Priority;
 use divvee::System;
+use doctor::DoctorCmd;
 use edit::EditCmd;
 use env_logger::Env;
+use goal::GoalCmd;
 use list::ListCmd;
 use log::debug;
+use prioritize::PrioritizeCmd;
 use reindex::ReindexCmd;
+use release::{ReleaseCmd, ReleaseNotesCmd};
+use report::ReportCmd;
+use risk::{AssessRiskCmd, RiskCmd};
 use show::ShowCmd;
 use std::path::PathBuf;
 use std::{env, str};
+use undo::{RevertCmd, UndoCmd};
+use watch::{InboxCmd, UnwatchCmd, WatchCmd};
 
+mod allocation;
+mod archive;
+mod burndown;
+mod chart;
 mod config;
 mod create;
+mod customer;
+mod decision;
+mod doctor;
 mod edit;
+mod field;
+mod goal;
 mod list;
 mod print;
+mod prioritize;
 mod reindex;
+mod release;
+mod report;
+mod risk;
 mod show;
+mod template;
+mod undo;
 mod util;
+mod watch;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -46,11 +76,45 @@ enum Cmd {
     List(ListCmd),
     Show(ShowCmd),
     Edit(EditCmd),
+    Prioritize(PrioritizeCmd),
+    /// Reports on throughput, cycle time, flow and load from task history
+    #[command(subcommand_required = true)]
+    Report(ReportCmd),
+    /// Unrecord your latest change, if it hasn't been pushed
+    Undo(UndoCmd),
+    /// Record a change that reverses an earlier one
+    Revert(RevertCmd),
+    /// Watch tasks for changes, or list watched tasks
+    Watch(WatchCmd),
+    Unwatch(UnwatchCmd),
+    /// Changes by others to watched and assigned tasks since the inbox was last read
+    Inbox(InboxCmd),
     // Search(SearchCmd),
     // Sync,
     // Link(LinkCmd),
     BulkEdit(BulkEditCmd),
     Reindex(ReindexCmd),
+    /// Check every task doc for problems
+    #[command(alias = "lint")]
+    Doctor(DoctorCmd),
+    /// Propose, list and accept decisions that tasks depend on
+    #[command(subcommand_required = true)]
+    Decision(DecisionCmd),
+    /// Set goals with key results and align work with them
+    #[command(subcommand_required = true)]
+    Goal(GoalCmd),
+    /// Record risks with an owner and mitigation
+    #[command(subcommand_required = true)]
+    Risk(RiskCmd),
+    /// Risk signals per milestone and epic: overdue, stale, blocked and unassigned work
+    AssessRisk(AssessRiskCmd),
+    /// Markdown notes for the completed tasks labeled for a release, e.g. r-1.4
+    ReleaseNotes(ReleaseNotesCmd),
+    /// Tag the repo for a release
+    #[command(subcommand_required = true)]
+    Release(ReleaseCmd),
+    /// Move closed tasks older than a cutoff to the team's archive dir
+    Archive(ArchiveCmd),
 }
 
 #[derive(Args, Debug, Default)]
@@ -61,11 +125,149 @@ struct FilterArgs {
     assignee: Option<String>,
     #[arg(long, short = 's', value_enum)]
     status: Option<StatusFilter>,
+    /// Only include tasks with at least this priority (low, medium, high, urgent)
+    #[arg(long, short = 'p')]
+    priority: Option<Priority>,
+    /// Only include tasks of this type (e.g. bug)
+    #[arg(long = "type")]
+    kind: Option<String>,
+    /// Only include tasks whose prop matches (e.g. severity=S1 or estimate>=3)
+    #[arg(long = "prop", value_name = "NAME=VALUE")]
+    props: Vec<PropFilter>,
+    /// Only include tasks due before this date (YYYY-MM-DD)
+    #[arg(long)]
+    due_before: Option<NaiveDate>,
+    /// Only include open tasks that are past due
+    #[arg(long)]
+    overdue: bool,
+    /// Only include tasks due this week
+    #[arg(long)]
+    due_this_week: bool,
+    /// Only include tasks waiting on a proposed decision (a `d-` label)
+    #[arg(long)]
+    blocked: bool,
+    /// Include archived tasks, which are otherwise left out unless an archive label (-A) is given
+    #[arg(long)]
+    include_archived: bool,
     #[command(flatten)]
     labels: Labels,
 }
 
+/// Comparison against an indexed prop, e.g. `estimate>=3`
+#[derive(Debug, Clone)]
+struct PropFilter {
+    name: String,
+    op: &'static str,
+    value: String,
+}
+
+impl str::FromStr for PropFilter {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<PropFilter> {
+        // two-character operators first, so `>=` isn't read as `>`
+        for op in [">=", "<=", "!=", "=", ">", "<"] {
+            if let Some((name, value)) = s.split_once(op) {
+                let name = name.trim().trim_start_matches("props.");
+                if name.is_empty() {
+                    bail!("Missing prop name in {}", s);
+                }
+                return Ok(PropFilter {
+                    name: name.to_owned(),
+                    op,
+                    value: value.trim().to_owned(),
+                });
+            }
+        }
+        bail!("Expected NAME=VALUE, found {}", s)
+    }
+}
+
+impl PropFilter {
+    fn to_where_clause(&self) -> String {
+        let (op, negate) = match self.op {
+            "!=" => ("=", true),
+            op => (op, false),
+        };
+        let cmp = match self.value.parse::<f64>() {
+            Ok(n) => format!("number {op} {n}"),
+            Err(_) => format!("value {op} {}", util::sql_str(&self.value)),
+        };
+        let subquery = format!(
+            "SELECT task_id FROM task_props WHERE name = {} AND {cmp}",
+            util::sql_str(&self.name)
+        );
+        match negate {
+            true => format!("id NOT IN ({subquery})"),
+            false => format!("id IN ({subquery})"),
+        }
+    }
+}
+
+/// Range of due dates, including `from` and excluding `to`
+#[derive(Debug, Clone, Copy, Default)]
+struct DueRange {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+impl DueRange {
+    /// Matches on the explicit due date, or the SLA due date for tasks without one
+    fn to_where_clause(self) -> String {
+        let due = "coalesce(due, sla_due)";
+        let mut parts = vec![format!("{due} IS NOT NULL")];
+        if let Some(from) = self.from {
+            parts.push(format!("{due} >= '{from}'"));
+        }
+        if let Some(to) = self.to {
+            parts.push(format!("{due} < '{to}'"));
+        }
+        parts.join(" AND ")
+    }
+}
+
 impl FilterArgs {
+    /// Maps customer labels given by display name or alias (e.g. `-C "Acme Corp"`) to
+    /// their registry key, keeping unknown customers as given
+    fn resolve_customers(&mut self, config: &RepoConfig) {
+        for label in &mut self.labels.0 {
+            if let Some(key) = label
+                .strip_prefix(CUSTOMER_PREFIX)
+                .and_then(|name| config.customer_key(name))
+            {
+                *label = format!("{CUSTOMER_PREFIX}{key}");
+            }
+        }
+    }
+
+    /// Due date range selected by the due filters, if any
+    fn due_range(&self) -> Option<DueRange> {
+        let today = Local::now().date_naive();
+        let mut range = None;
+        let mut narrow = |from: Option<NaiveDate>, to: Option<NaiveDate>| {
+            let r: &mut DueRange = range.get_or_insert_with(DueRange::default);
+            r.from = r.from.max(from);
+            r.to = match (r.to, to) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        };
+        if let Some(date) = self.due_before {
+            narrow(None, Some(date));
+        }
+        if self.overdue {
+            narrow(None, Some(today));
+        }
+        if self.due_this_week {
+            let monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+            narrow(Some(monday), Some(monday + Days::new(7)));
+        }
+        range
+    }
+
+    /// Builds the SQL filter
+    ///
+    /// Due filters match on the explicit due date, or the SLA due date when there is none;
+    /// tasks with neither never match.
     fn to_where_clause(&self) -> String {
         let mut parts = Vec::new();
         if let Some(team) = &self.team {
@@ -85,6 +287,37 @@ impl FilterArgs {
                 }
             };
             parts.push(clause.to_owned());
+        } else if self.overdue {
+            parts.push("(status IS null OR status = 'Todo' OR status = 'In Progress')".to_owned());
+        }
+        if let Some(range) = self.due_range() {
+            parts.push(format!("({})", range.to_where_clause()));
+        }
+        if let Some(priority) = self.priority {
+            parts.push(format!("priority >= {}", priority as i64));
+        }
+        if let Some(kind) = &self.kind {
+            parts.push(format!("type = {}", util::sql_str(kind)));
+        }
+        parts.extend(self.props.iter().map(PropFilter::to_where_clause));
+        parts.extend(self.labels.0.iter().map(|label| {
+            format!(
+                "id IN (SELECT task_id FROM task_labels WHERE label = {})",
+                util::sql_str(label)
+            )
+        }));
+        let wants_archived =
+            self.include_archived || self.labels.0.iter().any(|l| l.starts_with(ARCHIVE_PREFIX));
+        if !wants_archived {
+            parts.push(format!(
+                "id NOT IN (SELECT task_id FROM task_labels WHERE label LIKE '{ARCHIVE_PREFIX}%')"
+            ));
+        }
+        if self.blocked {
+            parts.push(format!(
+                "id IN (SELECT l.task_id FROM task_labels l JOIN decisions d ON l.label = '{}' || d.id WHERE d.status = 'proposed')",
+                divvee::decision::LABEL_PREFIX
+            ));
         }
         match parts.is_empty() {
             true => String::from("1 = 1"),
@@ -110,7 +343,7 @@ impl FromArgMatches for Labels {
             .filter_map(|c| {
                 matches
                     .get_one::<String>(&format!("{}-label", c))
-                    .map(|label| format!("{}-{}", c, label))
+                    .map(|label| format!("{}-{}", c.to_ascii_lowercase(), label))
             })
             .collect();
 
@@ -169,7 +402,8 @@ struct BulkEditCmd {
     // rm_opts: RemoveCmd,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Display)]
+/// Statuses in workflow order, which is also their sort order
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Display)]
 enum Status {
     #[display("Todo")]
     Todo,
@@ -205,6 +439,14 @@ impl str::FromStr for Status {
 }
 
 impl Status {
+    const ALL: [Status; 5] = [
+        Status::Todo,
+        Status::InProgress,
+        Status::Done,
+        Status::Canceled,
+        Status::Duplicate,
+    ];
+
     fn to_sym(&self) -> char {
         match self {
             Status::Todo => '○',
@@ -216,6 +458,12 @@ impl Status {
     }
 }
 
+impl serde::Serialize for Status {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum, Display)]
 enum View {
     #[display("id")]
@@ -226,6 +474,14 @@ enum View {
     Detail,
     #[display("json")]
     Json,
+    #[display("ndjson")]
+    Ndjson,
+    #[display("yaml")]
+    Yaml,
+    #[display("csv")]
+    Csv,
+    #[display("markdown")]
+    Markdown,
 }
 
 #[tokio::main]
@@ -258,7 +514,22 @@ async fn main() -> Result<()> {
         Some(Cmd::Edit(args)) => edit::run(&mut dv, args).await?,
         Some(Cmd::Show(args)) => show::run(&mut dv, args)?,
         Some(Cmd::List(args)) => list::run(&mut dv, args).await?,
+        Some(Cmd::Prioritize(args)) => prioritize::run(&mut dv, args).await?,
+        Some(Cmd::Report(args)) => report::run(&mut dv, args).await?,
+        Some(Cmd::Undo(args)) => undo::undo(&mut dv, args).await?,
+        Some(Cmd::Doctor(args)) => doctor::run(&mut dv, args).await?,
+        Some(Cmd::Revert(args)) => undo::revert(&mut dv, args).await?,
+        Some(Cmd::Watch(args)) => watch::watch(&mut dv, args).await?,
+        Some(Cmd::Unwatch(args)) => watch::unwatch(&mut dv, args).await?,
+        Some(Cmd::Inbox(args)) => watch::inbox(&mut dv, args).await?,
         Some(Cmd::Reindex(args)) => reindex::run(&mut dv, args).await?,
+        Some(Cmd::Decision(args)) => decision::run(&mut dv, args).await?,
+        Some(Cmd::Goal(args)) => goal::run(&mut dv, args).await?,
+        Some(Cmd::Risk(args)) => risk::run(&mut dv, args).await?,
+        Some(Cmd::AssessRisk(args)) => risk::assess(&mut dv, args).await?,
+        Some(Cmd::ReleaseNotes(args)) => release::notes(&dv, &args).await?,
+        Some(Cmd::Release(args)) => release::run(&mut dv, args).await?,
+        Some(Cmd::Archive(args)) => archive::run(&mut dv, args).await?,
         _ => unimplemented!("Command not implemented"),
     }
     Ok(())
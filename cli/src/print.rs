@@ -1,63 +1,205 @@
+use crate::field::{Field, GroupBy};
 use crate::{Status, View};
+use chrono::{Local, NaiveDate};
+use divvee::repo::ChangeInfo;
 use divvee::task::Task;
+use divvee::MetaDoc;
 use owo_colors::{OwoColorize, Style};
-use std::cmp::max;
+use std::io::{self, IsTerminal};
 use termimad::MadSkin;
 
-pub fn print_tasks(tasks: &[Task], view: View) {
+/// A task along with any history metadata that was loaded for it
+pub struct TaskRow {
+    pub task: Task,
+    pub meta: Option<(ChangeInfo, Option<ChangeInfo>)>,
+}
+
+impl TaskRow {
+    pub fn status(&self) -> Status {
+        self.task
+            .status
+            .as_ref()
+            .and_then(|s| s.parse::<Status>().ok())
+            .unwrap_or(Status::Todo)
+    }
+
+    pub fn created(&self) -> Option<&ChangeInfo> {
+        self.meta.as_ref().map(|(created, _)| created)
+    }
+
+    /// Last change to the task, which is the creation if it was never updated
+    pub fn updated(&self) -> Option<&ChangeInfo> {
+        self.meta
+            .as_ref()
+            .map(|(created, updated)| updated.as_ref().unwrap_or(created))
+    }
+
+    pub fn due(&self) -> Option<NaiveDate> {
+        self.task.due.or(self.task.sla_due)
+    }
+
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        matches!(self.status(), Status::Todo | Status::InProgress)
+            && self.due().is_some_and(|due| due < today)
+    }
+}
+
+impl From<Task> for TaskRow {
+    fn from(task: Task) -> TaskRow {
+        TaskRow { task, meta: None }
+    }
+}
+
+impl From<MetaDoc<Task>> for TaskRow {
+    fn from(doc: MetaDoc<Task>) -> TaskRow {
+        TaskRow {
+            task: doc.doc,
+            meta: Some((doc.created, doc.updated)),
+        }
+    }
+}
+
+pub fn print_tasks(rows: &[TaskRow], view: View, columns: &[Field], group_by: Option<&GroupBy>) {
     match view {
         View::Id => {
-            for id in tasks.iter().filter_map(|t| t.id()) {
+            for id in rows.iter().filter_map(|r| r.task.id()) {
                 println!("{id}");
             }
         }
         View::Line => {
-            let widths = tasks.iter().fold([0; 3], |acc, t| {
-                let w = widths(t);
-                [max(acc[0], w[0]), max(acc[1], w[1]), max(acc[2], w[2])]
-            });
-            print_header_line(widths);
-            for task in tasks {
-                print_task_line(task, widths);
+            let columns = or_default(columns);
+            let widths = widths(rows, &columns);
+            print_header_line(&columns, &widths);
+            match group_by {
+                None => {
+                    for row in rows {
+                        print_task_line(row, &columns, &widths);
+                    }
+                }
+                Some(group_by) => {
+                    // rows are expected to be sorted by group already
+                    let mut current = None;
+                    for row in rows {
+                        let key = group_by.key(row);
+                        if current.as_ref() != Some(&key) {
+                            let heading = key.as_deref().unwrap_or("(none)");
+                            println!("\n{}", heading.bold());
+                            current = Some(key);
+                        }
+                        print_task_line(row, &columns, &widths);
+                    }
+                }
             }
         }
         View::Detail => {
-            for task in tasks {
-                print_task_detail(task, false);
+            for row in rows {
+                print_task_detail(&row.task, false);
                 println!("---");
             }
         }
-        View::Json => println!("{}", serde_json::to_string(&tasks).unwrap()),
+        View::Json => {
+            let records: Vec<_> = rows.iter().map(|r| record(r, columns)).collect();
+            println!("{}", serde_json::to_string(&records).unwrap())
+        }
+        View::Ndjson => {
+            for row in rows {
+                println!("{}", serde_json::to_string(&record(row, columns)).unwrap());
+            }
+        }
+        View::Yaml => {
+            let records: Vec<_> = rows.iter().map(|r| record(r, columns)).collect();
+            print!("{}", serde_yaml::to_string(&records).unwrap())
+        }
+        View::Csv => print_csv(rows, &or_default(columns)),
+        View::Markdown => print_markdown(rows, &or_default(columns)),
     }
 }
 
-fn widths(task: &Task) -> [usize; 3] {
-    [
-        task.id().map(|s| s.len()).unwrap_or(0),
-        task.assignee.as_ref().map(|s| s.len()).unwrap_or(0),
-        task.title.len(),
-    ]
+fn widths(rows: &[TaskRow], columns: &[Field]) -> Vec<usize> {
+    columns
+        .iter()
+        .map(|col| {
+            rows.iter()
+                .map(|r| col.value(r).chars().count())
+                .chain([col.header().chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect()
 }
 
-pub fn print_task(task: &Task, view: View) {
+fn or_default(columns: &[Field]) -> Vec<Field> {
+    match columns.is_empty() {
+        true => Field::defaults(),
+        false => columns.to_vec(),
+    }
+}
+
+/// Serializable form of a row: the whole task, or only the selected columns
+fn record(row: &TaskRow, columns: &[Field]) -> serde_json::Value {
+    match columns.is_empty() {
+        true => serde_json::to_value(&row.task).unwrap(),
+        false => columns
+            .iter()
+            .map(|c| (c.to_string(), c.json_value(row)))
+            .collect(),
+    }
+}
+
+pub fn print_task(row: &TaskRow, view: View, columns: &[Field]) {
     match view {
-        View::Id => println!("{}", task.id().unwrap()),
-        View::Line => print_task_line(task, widths(task)),
-        View::Detail => print_task_detail(task, true),
-        View::Json => println!("{}", serde_json::to_string(&task).unwrap()),
+        View::Id => println!("{}", row.task.id().unwrap()),
+        View::Line => {
+            let columns = or_default(columns);
+            let widths = widths(std::slice::from_ref(row), &columns);
+            print_task_line(row, &columns, &widths)
+        }
+        View::Detail => print_task_detail(&row.task, true),
+        View::Json => println!("{}", serde_json::to_string(&record(row, columns)).unwrap()),
+        View::Ndjson => println!("{}", serde_json::to_string(&record(row, columns)).unwrap()),
+        View::Yaml => print!("{}", serde_yaml::to_string(&record(row, columns)).unwrap()),
+        View::Csv | View::Markdown => print_tasks(std::slice::from_ref(row), view, columns, None),
+    }
+}
+
+fn print_csv(rows: &[TaskRow], columns: &[Field]) {
+    let mut writer = csv::Writer::from_writer(io::stdout());
+    writer
+        .write_record(columns.iter().map(|c| c.header()))
+        .unwrap();
+    for row in rows {
+        writer
+            .write_record(columns.iter().map(|c| c.value(row)))
+            .unwrap();
+    }
+    writer.flush().unwrap();
+}
+
+fn print_markdown(rows: &[TaskRow], columns: &[Field]) {
+    let escape = |s: &str| s.replace('|', "\\|").replace('\n', " ");
+    let headers: Vec<String> = columns.iter().map(|c| escape(c.header())).collect();
+    println!("| {} |", headers.join(" | "));
+    println!("|{}", " --- |".repeat(columns.len()));
+    for row in rows {
+        let values: Vec<String> = columns.iter().map(|c| escape(&c.value(row))).collect();
+        println!("| {} |", values.join(" | "));
     }
 }
-fn print_header_line(widths: [usize; 3]) {
-    let [id_w, assignee_w, title_w] = widths;
+
+fn print_header_line(columns: &[Field], widths: &[usize]) {
     let s = format!(
-        "  {:id_w$}  {:assignee_w$}  {:title_w$}",
-        "ID", "Assignee", "Title",
+        "  {}",
+        format_columns(columns.iter().map(|c| c.header()), widths)
     );
-    println!("{}", s.underline());
+    println!("{}", fit_terminal(s).underline());
 }
-fn print_task_line(task: &Task, widths: [usize; 3]) {
-    let [id_w, assignee_w, title_w] = widths;
-    let status = task.status.as_ref().and_then(|s| s.parse::<Status>().ok());
+
+fn print_task_line(row: &TaskRow, columns: &[Field], widths: &[usize]) {
+    let status = row
+        .task
+        .status
+        .as_ref()
+        .and_then(|s| s.parse::<Status>().ok());
     let status_sym = status.map(|s| s.to_sym()).unwrap_or(' ');
 
     let style = match status.unwrap_or(Status::Todo) {
@@ -67,13 +209,38 @@ fn print_task_line(task: &Task, widths: [usize; 3]) {
         Status::Canceled => Style::new().dimmed().strikethrough(),
         Status::Duplicate => Style::new().dimmed(),
     };
+    let style = match row.is_overdue(Local::now().date_naive()) {
+        true => style.red(),
+        false => style,
+    };
+    let values: Vec<String> = columns.iter().map(|c| c.value(row)).collect();
     let s = format!(
-        "{status_sym} {:id_w$}  {:assignee_w$}  {:title_w$}",
-        task.id().unwrap(),
-        task.assignee.as_deref().unwrap_or(""),
-        task.title,
+        "{status_sym} {}",
+        format_columns(values.iter().map(String::as_str), widths)
     );
-    println!("{}", s.style(style));
+    println!("{}", fit_terminal(s).style(style));
+}
+
+fn format_columns<'a>(values: impl Iterator<Item = &'a str>, widths: &[usize]) -> String {
+    values
+        .zip(widths)
+        .map(|(v, &w)| format!("{v:w$}"))
+        .collect::<Vec<_>>()
+        .join("  ")
+        .trim_end()
+        .to_owned()
+}
+
+/// Truncates a line to the terminal width when writing to a terminal
+fn fit_terminal(s: String) -> String {
+    if !io::stdout().is_terminal() {
+        return s;
+    }
+    let width = termimad::terminal_size().0 as usize;
+    match s.char_indices().nth(width.saturating_sub(1)) {
+        Some((idx, _)) if s.chars().count() > width => format!("{}…", &s[..idx]),
+        _ => s,
+    }
 }
 
 trait OrNa {
@@ -92,6 +259,9 @@ fn print_task_detail(task: &Task, print_description: bool) {
         task.id().unwrap().bold(),
         task.title.bold().green()
     );
+    if let Some(kind) = &task.kind {
+        println!("Type: {kind}");
+    }
     println!("Status: {}", task.status.or_na().bold());
     println!("Assignee: {}", task.assignee.or_na());
 
@@ -0,0 +1,258 @@
+use crate::field::Field;
+use crate::print::TaskRow;
+use crate::{config, util, FilterArgs, StatusFilter};
+use anyhow::{bail, format_err, Result};
+use clap::Args;
+use divvee::task::{Priority, Task};
+use divvee::*;
+use log::debug;
+use tempfile::Builder;
+
+/// Spacing between ranks, leaving room to insert tasks without renumbering
+const RANK_STEP: i64 = 1024;
+
+#[derive(Args, Debug)]
+pub struct PrioritizeCmd {
+    /// Task to move
+    #[arg(required_unless_present = "interactive")]
+    id: Option<String>,
+    /// Move directly above this task (and into its priority)
+    #[arg(long, group = "position")]
+    above: Option<String>,
+    /// Move directly below this task (and into its priority)
+    #[arg(long, group = "position")]
+    below: Option<String>,
+    /// Move to the top of its priority
+    #[arg(long, group = "position")]
+    top: bool,
+    /// Move to the bottom of the ranked tasks in its priority
+    #[arg(long, group = "position")]
+    bottom: bool,
+    /// Set priority (none, low, medium, high, urgent)
+    #[arg(long, short = 'p')]
+    priority: Option<Priority>,
+    /// Reorder the open backlog of a team in an editor
+    #[arg(long, short = 'i', conflicts_with = "id")]
+    interactive: bool,
+    #[arg(long, short = 't')]
+    team: Option<String>,
+}
+
+pub async fn run(dv: &mut System, args: PrioritizeCmd) -> Result<()> {
+    match args.id.as_deref() {
+        Some(id) => move_task(dv, id, &args).await,
+        None => {
+            let team = match &args.team {
+                Some(team) => team.clone(),
+                None => config::default_team()?,
+            };
+            reorder_interactive(dv, &team).await
+        }
+    }
+}
+
+async fn move_task(dv: &System, id: &str, args: &PrioritizeCmd) -> Result<()> {
+    let (team, id) = util::team_and_id(id)?;
+    let task: Task = dv.read_doc(util::task_path(dv, &id)?)?;
+    let target = match args.above.as_ref().or(args.below.as_ref()) {
+        Some(target) => Some(dv.read_doc::<Task, _>(util::task_path(dv, target)?)?),
+        None => None,
+    };
+
+    let priority = args
+        .priority
+        .or(target.as_ref().map(|t| t.priority))
+        .unwrap_or(task.priority);
+
+    let mut tier: Vec<TaskRow> = backlog(dv, &team)
+        .await?
+        .into_iter()
+        .filter(|row| row.task.priority == priority && row.task.id() != task.id())
+        .collect();
+
+    let pos = match &target {
+        Some(target) => {
+            let idx = tier
+                .iter()
+                .position(|row| row.task.id() == target.id())
+                .ok_or_else(|| {
+                    format_err!("{} is not in the open backlog", target.id().unwrap())
+                })?;
+            match args.below.is_some() {
+                true => Some(idx + 1),
+                false => Some(idx),
+            }
+        }
+        None if args.top => Some(0),
+        None if args.bottom => Some(tier.iter().filter(|r| r.task.rank.is_some()).count()),
+        None => None,
+    };
+
+    let mut updates = Vec::new();
+    match pos {
+        None => {
+            if priority == task.priority {
+                println!("No changes were made");
+                return Ok(());
+            }
+            updates.push((id.clone(), priority, task.rank));
+        }
+        Some(pos) => {
+            let mut moved = TaskRow::from(task);
+            moved.task.rank = None;
+            tier.insert(pos, moved);
+            let ranks = ranks_for_insert(&tier, pos);
+            for (row, rank) in tier.iter().zip(ranks) {
+                let row_id = row.task.id().unwrap();
+                if row_id == id || row.task.rank != rank {
+                    updates.push((row_id, priority, rank));
+                }
+            }
+        }
+    }
+
+    apply(dv, updates).await
+}
+
+/// Open tasks of a team in backlog order
+async fn backlog(dv: &System, team: &str) -> Result<Vec<TaskRow>> {
+    let filters = FilterArgs {
+        team: Some(team.to_owned()),
+        status: Some(StatusFilter::Open),
+        ..FilterArgs::default()
+    };
+    let tasks: Vec<Task> = dv.query(&filters.to_where_clause()).await?;
+    let mut rows: Vec<TaskRow> = tasks.into_iter().map(TaskRow::from).collect();
+    rows.sort_by(|a, b| {
+        Field::Priority
+            .compare(a, b)
+            .then_with(|| Field::Id.compare(a, b))
+    });
+    Ok(rows)
+}
+
+/// Computes ranks after inserting an unranked task at `pos`
+///
+/// Prefers a rank between its neighbors, renumbering the whole tier
+/// when there is no room.
+fn ranks_for_insert(rows: &[TaskRow], pos: usize) -> Vec<Option<i64>> {
+    let mut ranks: Vec<Option<i64>> = rows.iter().map(|r| r.task.rank).collect();
+    let prev = pos.checked_sub(1).map(|i| ranks[i]);
+    let next = ranks.get(pos + 1).copied().flatten();
+
+    let between = match (prev, next) {
+        (None, None) => Some(RANK_STEP),
+        (None, Some(hi)) => Some(hi - RANK_STEP),
+        (Some(Some(lo)), None) => Some(lo + RANK_STEP),
+        (Some(Some(lo)), Some(hi)) if hi - lo > 1 => Some(lo + (hi - lo) / 2),
+        _ => None,
+    };
+    match between {
+        Some(rank) => ranks[pos] = Some(rank),
+        None => {
+            for (i, rank) in ranks.iter_mut().enumerate() {
+                *rank = Some(RANK_STEP * (i as i64 + 1));
+            }
+        }
+    }
+    ranks
+}
+
+async fn reorder_interactive(dv: &System, team: &str) -> Result<()> {
+    let rows = backlog(dv, team).await?;
+    if rows.is_empty() {
+        bail!("No open tasks for {}", team);
+    }
+
+    let mut text = String::from(
+        "# Reorder tasks to rank them. Move a task under another heading to change its priority.\n\
+         # Lines starting with '#' are ignored and removed tasks are left unchanged.\n",
+    );
+    for priority in Priority::ALL.into_iter().rev() {
+        text.push_str(&format!("\n## {priority}\n"));
+        for row in rows.iter().filter(|r| r.task.priority == priority) {
+            text.push_str(&format!("{}  {}\n", row.task.id().unwrap(), row.task.title));
+        }
+    }
+
+    let editted = edit::edit_with_builder(text, Builder::new().suffix(".md"))?;
+
+    let mut updates = Vec::new();
+    let mut priority = Priority::None;
+    let mut tier_pos = 0;
+    for line in editted.lines().map(str::trim) {
+        if let Some(heading) = line.strip_prefix("## ") {
+            priority = heading.trim().parse()?;
+            tier_pos = 0;
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let id = line.split_whitespace().next().unwrap();
+        let row = rows
+            .iter()
+            .find(|r| r.task.id().as_deref() == Some(id))
+            .ok_or_else(|| format_err!("{} is not in the open backlog", id))?;
+        tier_pos += 1;
+        let rank = Some(RANK_STEP * tier_pos);
+        if row.task.priority != priority || row.task.rank != rank {
+            updates.push((id.to_owned(), priority, rank));
+        }
+    }
+
+    apply(dv, updates).await
+}
+
+async fn apply(dv: &System, updates: Vec<(String, Priority, Option<i64>)>) -> Result<()> {
+    if updates.is_empty() {
+        println!("No changes were made");
+        return Ok(());
+    }
+    let mut docs = Vec::new();
+    let mut ids = Vec::new();
+    for (id, priority, rank) in updates {
+        let path = util::task_path(dv, &id)?;
+        let mut task: Task = dv.read_doc(&path)?;
+        debug!("prioritize {id}: {priority} rank={rank:?}");
+        task.priority = priority;
+        task.rank = rank;
+        docs.push((path, task));
+        ids.push(id);
+    }
+    dv.update_docs(docs, &format!("Prioritized {}", ids.join(", ")))
+        .await?;
+    for id in ids {
+        println!("Updated {id}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tier(ranks: &[Option<i64>]) -> Vec<TaskRow> {
+        ranks
+            .iter()
+            .map(|&rank| {
+                let mut task = Task::new("task");
+                task.rank = rank;
+                TaskRow::from(task)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ranks_for_insert_between() {
+        let ranks = ranks_for_insert(&tier(&[Some(10), None, Some(20)]), 1);
+        assert_eq!(ranks, vec![Some(10), Some(15), Some(20)]);
+    }
+
+    #[test]
+    fn test_ranks_for_insert_no_gap() {
+        let ranks = ranks_for_insert(&tier(&[Some(10), Some(11), None, Some(12)]), 2);
+        let ranks: Vec<i64> = ranks.into_iter().map(Option::unwrap).collect();
+        assert!(ranks.windows(2).all(|w| w[0] < w[1]), "{ranks:?}");
+    }
+}
@@ -1,8 +1,7 @@
 use anyhow::Result;
 use clap::Args;
-use divvee::{task::Task, System};
+use divvee::{collection::Collection, task::Task, System};
 use log::debug;
-use std::path::PathBuf;
 
 #[derive(Args, Debug)]
 pub struct ReindexCmd {
@@ -13,22 +12,15 @@ pub async fn run(dv: &mut System, args: ReindexCmd) -> Result<()> {
     let mut split = args.target.split('-');
 
     let team = split.next().unwrap();
-    let base_dir = PathBuf::from(team).join("tasks");
 
     if let Some(id_num) = split.next() {
-        let path = base_dir.join(format!("{team}-{id_num}.md"));
+        let path = dv.doc_path(Task::KIND.dir(team), &format!("{team}-{id_num}"));
         debug!("Reindexing {}", path.display());
         dv.reindex::<Task>(&path).await?;
     } else {
-        debug!("Processing {}", base_dir.display());
-        for doc in dv.read_dir(&base_dir)? {
-            if let Some(fname) = doc.repo_path().file_name() {
-                if fname.to_string_lossy().starts_with(&team) {
-                    debug!("Reindexing {}", doc.repo_path().display());
-                    dv.reindex::<Task>(&doc.repo_path()).await?;
-                }
-            }
-        }
+        debug!("Reindexing every collection of {team}");
+        let count = dv.reindex_team(team).await?;
+        debug!("Reindexed {count} docs");
     }
 
     println!("Indexing complete");
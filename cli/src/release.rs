@@ -0,0 +1,226 @@
+use crate::burndown::has_label;
+use crate::report::{self, TaskHistory};
+use crate::{template, Status};
+use anyhow::{bail, Result};
+use chrono::{Local, NaiveDate};
+use clap::{Args, Subcommand};
+use divvee::task::Task;
+use divvee::System;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Prefix of the labels of releases, e.g. `r-1.4`
+const RELEASE_PREFIX: &str = "r-";
+
+/// Name of the template in the repo's templates/ dir that overrides the default notes
+const TEMPLATE_NAME: &str = "release-notes";
+
+const DEFAULT_TEMPLATE: &str = "# {{ release }} ({{ date }})
+{% for group in groups %}
+## {{ group.title }}
+
+{% for task in group.tasks -%}
+- {{ task.title }} ({{ task.id }})
+{% endfor %}
+{%- endfor %}
+{%- if contributors %}
+## Contributors
+
+{% for contributor in contributors -%}
+- {{ contributor }}
+{% endfor %}
+{%- endif %}";
+
+/// Headings of the sections of the notes, with the task types and labels that go in each.
+/// Anything else goes under "Other".
+const GROUPS: [(&str, &[&str]); 3] = [
+    ("Features", &["feature", "story", "enhancement"]),
+    ("Fixes", &["bug", "fix"]),
+    ("Chores", &["chore", "refactor", "docs"]),
+];
+
+#[derive(Args, Debug)]
+pub struct ReleaseNotesCmd {
+    /// Release label, e.g. r-1.4. The r- prefix may be left out
+    release: String,
+    /// Only include these teams. Defaults to every team in the repo
+    #[arg(long = "team", short = 't')]
+    teams: Vec<String>,
+    /// Template string, template file, or name of a template in the repo's templates/ dir.
+    /// Defaults to templates/release-notes if there is one
+    #[arg(long, short = 'f')]
+    format: Option<String>,
+    /// Write the notes to a file instead of stdout
+    #[arg(long)]
+    out: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ReleaseCmd {
+    #[command(subcommand)]
+    action: ReleaseAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum ReleaseAction {
+    /// Tag the current state of the repo as a release
+    Cut(CutArgs),
+}
+
+#[derive(Args, Debug)]
+struct CutArgs {
+    /// Release label, e.g. r-1.4. The r- prefix may be left out
+    release: String,
+    /// Tag even if work labeled for the release is still open
+    #[arg(long)]
+    force: bool,
+}
+
+/// Model exposed to release notes templates
+#[derive(Serialize)]
+struct ReleaseNotes {
+    release: String,
+    date: NaiveDate,
+    groups: Vec<Group>,
+    /// Everyone who recorded a change to a task in the release
+    contributors: BTreeSet<String>,
+}
+
+#[derive(Serialize)]
+struct Group {
+    title: &'static str,
+    tasks: Vec<Entry>,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    id: String,
+    title: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+    labels: Vec<String>,
+    completed: Option<NaiveDate>,
+    contributors: BTreeSet<String>,
+}
+
+/// Release label with its prefix, e.g. `r-1.4` for `1.4`
+fn release_label(release: &str) -> String {
+    match release.starts_with(RELEASE_PREFIX) {
+        true => release.to_owned(),
+        false => format!("{RELEASE_PREFIX}{release}"),
+    }
+}
+
+/// Section of the notes a task goes in, by type and then by label
+fn group_of(task: &Task) -> &'static str {
+    let names = task.kind.iter().chain(&task.labels);
+    for name in names {
+        let name = name.to_lowercase();
+        if let Some((title, _)) = GROUPS.iter().find(|(_, names)| names.contains(&&*name)) {
+            return title;
+        }
+    }
+    "Other"
+}
+
+fn entry(history: &TaskHistory) -> Option<Entry> {
+    let task = history.current()?;
+    Some(Entry {
+        id: task.id()?,
+        title: task.title.clone(),
+        kind: task.kind.clone(),
+        labels: task.labels.clone(),
+        completed: history.completed().map(|t| t.date_naive()),
+        contributors: history
+            .revisions
+            .iter()
+            .flat_map(|r| r.change.authors.iter().cloned())
+            .collect(),
+    })
+}
+
+pub async fn notes(dv: &System, args: &ReleaseNotesCmd) -> Result<()> {
+    let release = release_label(&args.release);
+    let teams = report::teams_or_all(dv, &args.teams)?;
+    let mut groups: Vec<Group> = GROUPS
+        .iter()
+        .map(|(title, _)| title)
+        .chain(&["Other"])
+        .map(|title| Group {
+            title,
+            tasks: Vec::new(),
+        })
+        .collect();
+    let mut contributors = BTreeSet::new();
+    for team in &teams {
+        for history in report::load_history(dv, team)? {
+            let Some(task) = history.current() else {
+                continue;
+            };
+            if !has_label(task, &release) || report::status(task) != Status::Done {
+                continue;
+            }
+            let Some(entry) = entry(&history) else {
+                continue;
+            };
+            let title = group_of(task);
+            contributors.extend(entry.contributors.iter().cloned());
+            if let Some(group) = groups.iter_mut().find(|g| g.title == title) {
+                group.tasks.push(entry);
+            }
+        }
+    }
+    groups.retain(|g| !g.tasks.is_empty());
+    if groups.is_empty() {
+        bail!("No completed tasks labeled {release}");
+    }
+    for group in &mut groups {
+        group.tasks.sort_by_key(|e| e.completed);
+    }
+
+    let source = match &args.format {
+        Some(format) => template::resolve(dv.root(), format)?,
+        None => {
+            template::find(dv.root(), TEMPLATE_NAME)?.unwrap_or_else(|| DEFAULT_TEMPLATE.to_owned())
+        }
+    };
+    let notes = ReleaseNotes {
+        release,
+        date: Local::now().date_naive(),
+        groups,
+        contributors,
+    };
+    let content = template::render(&source, notes)?;
+    report::write_output(args.out.as_deref(), &content)
+}
+
+pub async fn run(dv: &mut System, args: ReleaseCmd) -> Result<()> {
+    match args.action {
+        ReleaseAction::Cut(args) => cut(dv, args),
+    }
+}
+
+fn cut(dv: &System, args: CutArgs) -> Result<()> {
+    let release = release_label(&args.release);
+    let mut open = Vec::new();
+    for team in dv.teams()? {
+        open.extend(
+            dv.read_docs::<Task>(&team)?
+                .into_iter()
+                .filter(|t| has_label(t, &release))
+                .filter(|t| matches!(report::status(t), Status::Todo | Status::InProgress))
+                .filter_map(|t| t.id()),
+        );
+    }
+    if !open.is_empty() && !args.force {
+        bail!(
+            "{} still open in {release}: {}. Use --force to tag anyway",
+            open.len(),
+            open.join(", ")
+        );
+    }
+    let state = dv.tag(&format!("Release {release}"))?;
+    println!("Tagged {release} at {state}");
+    Ok(())
+}
@@ -0,0 +1,661 @@
+use crate::allocation::{self, AllocationCmd};
+use crate::burndown::{self, BurndownCmd};
+use crate::chart::{self, Series};
+use crate::customer::{self, CustomerCmd};
+use crate::goal::{self, GoalReportArgs};
+use crate::util::end_of_day;
+use crate::{config, Status};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Utc};
+use clap::{Args, Subcommand, ValueEnum};
+use divvee::collection::Collection;
+use divvee::task::Task;
+use divvee::{DocRevision, System};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Args, Debug)]
+pub struct ReportCmd {
+    #[command(subcommand)]
+    kind: ReportKind,
+}
+
+#[derive(Subcommand, Debug)]
+enum ReportKind {
+    /// Tasks completed per week
+    Throughput(ReportArgs),
+    /// Lead time (created to done) and cycle time (started to done) of completed tasks
+    CycleTime(ReportArgs),
+    /// Number of tasks in each status at the end of each week
+    Flow(ReportArgs),
+    /// Open, in-progress and completed work per assignee
+    Load(ReportArgs),
+    /// All of the above
+    Summary(ReportArgs),
+    /// Remaining (or completed) work per day for a sprint or milestone
+    Burndown(BurndownCmd),
+    /// Work aligned with each goal, and in-progress work aligned with none
+    Goals(GoalReportArgs),
+    /// Open work per person and team against capacity, split by goal, epic and customer
+    Allocation(AllocationCmd),
+    /// Open requests and recently shipped work per customer (c- labels)
+    Customer(CustomerCmd),
+}
+
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    #[arg(long, short = 't')]
+    team: Option<String>,
+    /// Number of weeks to report on, including the current week
+    #[arg(long, short = 'w', default_value_t = 8)]
+    weeks: u32,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct OutputArgs {
+    #[arg(long, short = 'o', value_enum, default_value_t = ReportOutput::Text)]
+    pub output: ReportOutput,
+    /// Write the report to a file instead of stdout
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    /// Renders and writes a report that has no chart of its own, so no SVG output
+    pub fn write_report<T: Serialize>(
+        &self,
+        report: &T,
+        to_text: impl FnOnce() -> String,
+        to_html: impl FnOnce() -> String,
+    ) -> Result<()> {
+        let content = match self.output {
+            ReportOutput::Text => to_text(),
+            ReportOutput::Json => format!("{}\n", serde_json::to_string_pretty(report)?),
+            ReportOutput::Html => to_html(),
+            ReportOutput::Svg => bail!("SVG output is only available for burndown charts"),
+        };
+        write_output(self.out.as_deref(), &content)
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ReportOutput {
+    Text,
+    Json,
+    /// Standalone HTML page with SVG charts
+    Html,
+    /// Bare SVG chart (burndown only)
+    Svg,
+}
+
+/// Every revision of a task, oldest first
+pub struct TaskHistory {
+    pub revisions: Vec<DocRevision<Task>>,
+}
+
+impl TaskHistory {
+    /// Task as it was at `time`, or `None` if it didn't exist yet or was deleted
+    pub fn at(&self, time: DateTime<Utc>) -> Option<&Task> {
+        self.revisions
+            .iter()
+            .take_while(|r| r.change.timestamp <= time)
+            .last()
+            .and_then(|r| r.doc.as_ref())
+    }
+
+    pub fn current(&self) -> Option<&Task> {
+        self.revisions.last().and_then(|r| r.doc.as_ref())
+    }
+
+    pub fn status_at(&self, time: DateTime<Utc>) -> Option<Status> {
+        self.at(time).map(status)
+    }
+
+    pub fn created(&self) -> Option<DateTime<Utc>> {
+        self.revisions.first().map(|r| r.change.timestamp)
+    }
+
+    /// First time the task moved to In Progress
+    pub fn started(&self) -> Option<DateTime<Utc>> {
+        self.revisions
+            .iter()
+            .find(|r| r.doc.as_ref().map(status) == Some(Status::InProgress))
+            .map(|r| r.change.timestamp)
+    }
+
+    /// When the task was last moved to Done, if it is still done
+    pub fn completed(&self) -> Option<DateTime<Utc>> {
+        if self.current().map(status) != Some(Status::Done) {
+            return None;
+        }
+        let reopened = self
+            .revisions
+            .iter()
+            .rposition(|r| r.doc.as_ref().map(status) != Some(Status::Done));
+        let idx = reopened.map(|i| i + 1).unwrap_or(0);
+        Some(self.revisions[idx].change.timestamp)
+    }
+}
+
+pub fn status(task: &Task) -> Status {
+    task.status
+        .as_ref()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(Status::Todo)
+}
+
+/// Task prop holding the estimate that burndowns and allocation sum up
+const ESTIMATE_PROP: &str = "estimate";
+
+pub fn estimate(task: &Task) -> Option<f64> {
+    task.props.get(ESTIMATE_PROP).and_then(|v| match v {
+        serde_yaml::Value::Number(n) => n.as_f64(),
+        serde_yaml::Value::String(s) => s.parse().ok(),
+        _ => None,
+    })
+}
+
+/// Loads the history of every task in a team
+pub fn load_history(dv: &System, team: &str) -> Result<Vec<TaskHistory>> {
+    let prefix = format!("{team}-");
+    let history = dv.history::<Task, _>(Task::KIND.dir(team))?;
+    Ok(history
+        .into_iter()
+        .filter(|(path, _)| {
+            path.file_stem()
+                .is_some_and(|s| s.to_string_lossy().starts_with(&prefix))
+        })
+        .map(|(_, revisions)| TaskHistory { revisions })
+        .collect())
+}
+
+/// Writes a report to `--out` or stdout
+/// Teams named on the command line, or every team in the repo when none are
+pub fn teams_or_all(dv: &System, teams: &[String]) -> Result<Vec<String>> {
+    match teams.is_empty() {
+        true => Ok(dv.teams()?),
+        false => Ok(teams.to_vec()),
+    }
+}
+
+pub fn write_output(out: Option<&Path>, content: &str) -> Result<()> {
+    match out {
+        Some(path) => {
+            fs::write(path, content)?;
+            println!("Wrote {}", path.display());
+        }
+        None => print!("{content}"),
+    }
+    Ok(())
+}
+
+/// Table rows as text with every column left-aligned, since titles read badly otherwise
+pub fn left_aligned(table: &Table) -> String {
+    let widths: Vec<usize> = (0..table.headers.len())
+        .map(|i| {
+            table
+                .rows
+                .iter()
+                .map(|r| r[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut out = String::new();
+    for row in &table.rows {
+        let cols: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(v, &w)| format!("{v:w$}"))
+            .collect();
+        let _ = writeln!(out, "  {}", cols.join("  ").trim_end());
+    }
+    out
+}
+
+/// Standalone HTML page around report sections
+pub fn html_page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n\
+         body {{ font-family: sans-serif; margin: 2em; color: #222; }}\n\
+         table {{ border-collapse: collapse; margin: 1em 0; }}\n\
+         th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: right; }}\n\
+         th:first-child, td:first-child {{ text-align: left; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+        title = chart::escape(title),
+    )
+}
+
+/// A table of values, rendered as aligned text or HTML
+#[derive(Serialize)]
+pub struct Table {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(headers: &[&str]) -> Table {
+        Table {
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let widths: Vec<usize> = (0..self.headers.len())
+            .map(|i| {
+                self.rows
+                    .iter()
+                    .map(|r| r[i].chars().count())
+                    .chain([self.headers[i].chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let line = |values: &[String]| {
+            let cols: Vec<String> = values
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(i, (v, &w))| match i {
+                    0 => format!("{v:w$}"),
+                    _ => format!("{v:>w$}"),
+                })
+                .collect();
+            format!("{}\n", cols.join("  ").trim_end())
+        };
+        let mut out = line(&self.headers);
+        for row in &self.rows {
+            out.push_str(&line(row));
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let cells = |tag: &str, values: &[String]| {
+            let cells: String = values
+                .iter()
+                .map(|v| format!("<{tag}>{}</{tag}>", chart::escape(v)))
+                .collect();
+            format!("<tr>{cells}</tr>\n")
+        };
+        let mut out = String::from("<table>\n");
+        out.push_str(&cells("th", &self.headers));
+        for row in &self.rows {
+            out.push_str(&cells("td", row));
+        }
+        out.push_str("</table>\n");
+        out
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    team: String,
+    generated: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    throughput: Option<Vec<WeekCount>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cycle_time: Option<CycleTimes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flow: Option<Vec<FlowPoint>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    load: Option<Vec<Load>>,
+}
+
+#[derive(Serialize)]
+struct WeekCount {
+    week: NaiveDate,
+    completed: usize,
+}
+
+#[derive(Serialize)]
+struct CycleTimes {
+    lead_time: Distribution,
+    cycle_time: Distribution,
+}
+
+/// Summary of durations in days
+#[derive(Serialize, Default)]
+struct Distribution {
+    count: usize,
+    min: f64,
+    median: f64,
+    p85: f64,
+    max: f64,
+    mean: f64,
+    /// Number of tasks taking up to 1, 2, 4, 7, 14, 30 days and longer
+    histogram: Vec<(String, usize)>,
+}
+
+const BUCKETS: [(f64, &str); 6] = [
+    (1.0, "≤1d"),
+    (2.0, "≤2d"),
+    (4.0, "≤4d"),
+    (7.0, "≤1w"),
+    (14.0, "≤2w"),
+    (30.0, "≤30d"),
+];
+
+impl Distribution {
+    fn new(mut days: Vec<f64>) -> Distribution {
+        if days.is_empty() {
+            return Distribution::default();
+        }
+        days.sort_by(f64::total_cmp);
+        let percentile = |p: f64| days[((days.len() - 1) as f64 * p).round() as usize];
+        let mut histogram: Vec<(String, usize)> = BUCKETS
+            .iter()
+            .map(|(_, label)| (label.to_string(), 0))
+            .collect();
+        histogram.push((">30d".to_owned(), 0));
+        for d in &days {
+            let idx = BUCKETS
+                .iter()
+                .position(|(limit, _)| d <= limit)
+                .unwrap_or(BUCKETS.len());
+            histogram[idx].1 += 1;
+        }
+        Distribution {
+            count: days.len(),
+            min: days[0],
+            median: percentile(0.5),
+            p85: percentile(0.85),
+            max: days[days.len() - 1],
+            mean: days.iter().sum::<f64>() / days.len() as f64,
+            histogram,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FlowPoint {
+    week: NaiveDate,
+    counts: BTreeMap<Status, usize>,
+}
+
+#[derive(Serialize)]
+struct Load {
+    assignee: String,
+    todo: usize,
+    in_progress: usize,
+    completed: usize,
+}
+
+pub async fn run(dv: &mut System, args: ReportCmd) -> Result<()> {
+    let kind = &args.kind;
+    let args = match kind {
+        ReportKind::Throughput(a)
+        | ReportKind::CycleTime(a)
+        | ReportKind::Flow(a)
+        | ReportKind::Load(a)
+        | ReportKind::Summary(a) => a,
+        ReportKind::Burndown(args) => return burndown::run(dv, args),
+        ReportKind::Goals(args) => return goal::report(dv, args).await,
+        ReportKind::Allocation(args) => return allocation::run(dv, args).await,
+        ReportKind::Customer(args) => return customer::run(dv, args).await,
+    };
+    let team = match &args.team {
+        Some(team) => team.clone(),
+        None => config::default_team()?,
+    };
+    let history = load_history(dv, &team)?;
+
+    let today = Local::now().date_naive();
+    let this_week = today - Days::new(today.weekday().num_days_from_monday().into());
+    let weeks: Vec<NaiveDate> = (0..args.weeks.max(1))
+        .rev()
+        .map(|i| this_week - Days::new(7 * u64::from(i)))
+        .collect();
+    let since = end_of_day(weeks[0] - Days::new(1));
+
+    let all = matches!(kind, ReportKind::Summary(_));
+    let report = Report {
+        team: team.clone(),
+        generated: Utc::now(),
+        throughput: (all || matches!(kind, ReportKind::Throughput(_)))
+            .then(|| throughput(&history, &weeks)),
+        cycle_time: (all || matches!(kind, ReportKind::CycleTime(_)))
+            .then(|| cycle_times(&history, since)),
+        flow: (all || matches!(kind, ReportKind::Flow(_))).then(|| flow(&history, &weeks, today)),
+        load: (all || matches!(kind, ReportKind::Load(_))).then(|| load(&history, since)),
+    };
+
+    args.output.write_report(
+        &report,
+        || report.to_text(),
+        || html_page(&format!("{team} report"), &report.to_html()),
+    )
+}
+
+fn throughput(history: &[TaskHistory], weeks: &[NaiveDate]) -> Vec<WeekCount> {
+    weeks
+        .iter()
+        .map(|&week| {
+            let (start, end) = (
+                end_of_day(week - Days::new(1)),
+                end_of_day(week + Days::new(6)),
+            );
+            let completed = history
+                .iter()
+                .filter_map(TaskHistory::completed)
+                .filter(|&t| t > start && t <= end)
+                .count();
+            WeekCount { week, completed }
+        })
+        .collect()
+}
+
+fn cycle_times(history: &[TaskHistory], since: DateTime<Utc>) -> CycleTimes {
+    let days = |from: DateTime<Utc>, to: DateTime<Utc>| (to - from).num_minutes() as f64 / 1440.0;
+    let (mut lead, mut cycle) = (Vec::new(), Vec::new());
+    for task in history {
+        let Some(done) = task.completed().filter(|&t| t > since) else {
+            continue;
+        };
+        if let Some(created) = task.created() {
+            lead.push(days(created, done));
+        }
+        if let Some(started) = task.started().filter(|&t| t <= done) {
+            cycle.push(days(started, done));
+        }
+    }
+    CycleTimes {
+        lead_time: Distribution::new(lead),
+        cycle_time: Distribution::new(cycle),
+    }
+}
+
+fn flow(history: &[TaskHistory], weeks: &[NaiveDate], today: NaiveDate) -> Vec<FlowPoint> {
+    weeks
+        .iter()
+        .map(|&week| {
+            let end = end_of_day((week + Days::new(6)).min(today));
+            let mut counts: BTreeMap<Status, usize> = BTreeMap::new();
+            for status in history.iter().filter_map(|t| t.status_at(end)) {
+                *counts.entry(status).or_default() += 1;
+            }
+            FlowPoint { week, counts }
+        })
+        .collect()
+}
+
+fn load(history: &[TaskHistory], since: DateTime<Utc>) -> Vec<Load> {
+    let mut load: BTreeMap<String, Load> = BTreeMap::new();
+    for task_history in history {
+        let Some(task) = task_history.current() else {
+            continue;
+        };
+        let assignee = task
+            .assignee
+            .clone()
+            .unwrap_or_else(|| "(unassigned)".into());
+        let entry = load.entry(assignee.clone()).or_insert_with(|| Load {
+            assignee,
+            todo: 0,
+            in_progress: 0,
+            completed: 0,
+        });
+        match status(task) {
+            Status::Todo => entry.todo += 1,
+            Status::InProgress => entry.in_progress += 1,
+            Status::Done if task_history.completed().is_some_and(|t| t > since) => {
+                entry.completed += 1
+            }
+            _ => {}
+        }
+    }
+    load.into_values()
+        .filter(|l| l.todo + l.in_progress + l.completed > 0)
+        .collect()
+}
+
+fn fmt_days(d: f64) -> String {
+    format!("{d:.1}")
+}
+
+impl Report {
+    fn tables(&self) -> Vec<(&'static str, Table, Option<String>)> {
+        let mut sections = Vec::new();
+        if let Some(weeks) = &self.throughput {
+            let mut table = Table::new(&["Week", "Completed"]);
+            for w in weeks {
+                table
+                    .rows
+                    .push(vec![w.week.to_string(), w.completed.to_string()]);
+            }
+            let labels: Vec<String> = weeks
+                .iter()
+                .map(|w| w.week.format("%m-%d").to_string())
+                .collect();
+            let values = weeks.iter().map(|w| w.completed as f64).collect();
+            let svg = chart::stacked_bars(&labels, &[Series::new("Completed", values)]);
+            sections.push(("Throughput", table, Some(svg)));
+        }
+        if let Some(times) = &self.cycle_time {
+            let mut table = Table::new(&["Days", "Count", "Min", "Median", "85th", "Max", "Mean"]);
+            for (name, d) in [
+                ("Lead time", &times.lead_time),
+                ("Cycle time", &times.cycle_time),
+            ] {
+                table.rows.push(vec![
+                    name.to_owned(),
+                    d.count.to_string(),
+                    fmt_days(d.min),
+                    fmt_days(d.median),
+                    fmt_days(d.p85),
+                    fmt_days(d.max),
+                    fmt_days(d.mean),
+                ]);
+            }
+            let labels: Vec<String> = times
+                .lead_time
+                .histogram
+                .iter()
+                .map(|(l, _)| l.clone())
+                .collect();
+            let svg = (!labels.is_empty()).then(|| {
+                let series = [
+                    ("Lead time", &times.lead_time),
+                    ("Cycle time", &times.cycle_time),
+                ]
+                .map(|(name, d)| {
+                    let mut values: Vec<f64> = d.histogram.iter().map(|(_, n)| *n as f64).collect();
+                    values.resize(labels.len(), 0.0);
+                    Series::new(name, values)
+                });
+                chart::stacked_bars(&labels, &series)
+            });
+            sections.push(("Lead and cycle time", table, svg));
+        }
+        if let Some(points) = &self.flow {
+            let mut headers = vec!["Week"];
+            let statuses = Status::ALL;
+            let names: Vec<String> = statuses.iter().map(Status::to_string).collect();
+            headers.extend(names.iter().map(String::as_str));
+            let mut table = Table::new(&headers);
+            for p in points {
+                let mut row = vec![p.week.to_string()];
+                row.extend(
+                    statuses
+                        .iter()
+                        .map(|s| p.counts.get(s).copied().unwrap_or(0).to_string()),
+                );
+                table.rows.push(row);
+            }
+            let labels: Vec<String> = points
+                .iter()
+                .map(|p| p.week.format("%m-%d").to_string())
+                .collect();
+            // stack closed work at the bottom, like a cumulative flow diagram
+            let series: Vec<Series> = statuses
+                .iter()
+                .rev()
+                .map(|s| {
+                    let values = points
+                        .iter()
+                        .map(|p| p.counts.get(s).copied().unwrap_or(0) as f64)
+                        .collect();
+                    Series::new(s.to_string(), values)
+                })
+                .collect();
+            sections.push((
+                "Cumulative flow",
+                table,
+                Some(chart::stacked_bars(&labels, &series)),
+            ));
+        }
+        if let Some(load) = &self.load {
+            let mut table = Table::new(&["Assignee", "Todo", "In Progress", "Completed"]);
+            for l in load {
+                table.rows.push(vec![
+                    l.assignee.clone(),
+                    l.todo.to_string(),
+                    l.in_progress.to_string(),
+                    l.completed.to_string(),
+                ]);
+            }
+            let labels: Vec<String> = load.iter().map(|l| l.assignee.clone()).collect();
+            let series = [
+                Series::new(
+                    "In Progress",
+                    load.iter().map(|l| l.in_progress as f64).collect(),
+                ),
+                Series::new("Todo", load.iter().map(|l| l.todo as f64).collect()),
+            ];
+            sections.push(("Load", table, Some(chart::stacked_bars(&labels, &series))));
+        }
+        sections
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (i, (title, table, _)) in self.tables().into_iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            let _ = writeln!(out, "{title}\n");
+            out.push_str(&table.to_text());
+        }
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let mut out = String::new();
+        for (title, table, svg) in self.tables() {
+            let _ = writeln!(out, "<h2>{title}</h2>");
+            if let Some(svg) = svg {
+                out.push_str(&svg);
+                out.push('\n');
+            }
+            out.push_str(&table.to_html());
+        }
+        out
+    }
+}
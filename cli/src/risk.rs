@@ -0,0 +1,550 @@
+use crate::burndown::{first_labeled, has_label};
+use crate::goal::EPIC_PREFIX;
+use crate::report::{self, left_aligned, OutputArgs, Table, TaskHistory};
+use crate::{chart, config, util, Status};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Days, Local, NaiveDate, Utc};
+use clap::{Args, Subcommand};
+use divvee::collection::Collection;
+use divvee::decision::{self, Decision, DecisionStatus};
+use divvee::risk::{Risk, RiskLevel, RiskStatus};
+use divvee::task::{Priority, Task};
+use divvee::{RepoDoc, System};
+use log::debug;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Prefix of the labels of milestones, e.g. `m-launch`
+const MILESTONE_PREFIX: &str = "m-";
+
+/// Task prop listing the tasks that have to be done first, e.g. `blocked_by: [DIV-3]`
+const BLOCKED_BY_PROP: &str = "blocked_by";
+
+#[derive(Args, Debug)]
+pub struct RiskCmd {
+    #[command(subcommand)]
+    action: RiskAction,
+}
+
+#[derive(Subcommand, Debug)]
+enum RiskAction {
+    /// Record a risk
+    New(NewArgs),
+    /// List open risks
+    List(ListArgs),
+    /// Mark a risk as mitigated or closed
+    Close(CloseArgs),
+}
+
+#[derive(Args, Debug)]
+struct NewArgs {
+    title: String,
+    #[arg(long, short = 't')]
+    team: Option<String>,
+    /// Opens the editor if not given
+    #[arg(long, short = 'd')]
+    description: Option<String>,
+    /// low, medium or high
+    #[arg(long, default_value_t = RiskLevel::Medium)]
+    severity: RiskLevel,
+    /// Who watches the risk. Defaults to you
+    #[arg(long)]
+    owner: Option<String>,
+    #[arg(long)]
+    mitigation: Option<String>,
+    /// Milestone or epic the risk concerns (e.g. m-launch). Can be given more than once
+    #[arg(long = "label", short = 'l')]
+    labels: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct ListArgs {
+    /// Include mitigated and closed risks
+    #[arg(long)]
+    all: bool,
+}
+
+#[derive(Args, Debug)]
+struct CloseArgs {
+    id: String,
+    /// Keep watching the risk, but record that it's been mitigated
+    #[arg(long)]
+    mitigated: bool,
+    /// How the risk was mitigated
+    #[arg(long)]
+    mitigation: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct AssessRiskCmd {
+    /// Only assess this milestone or epic (e.g. m-launch)
+    scope: Option<String>,
+    /// Only include these teams. Defaults to every team in the repo
+    #[arg(long = "team", short = 't')]
+    teams: Vec<String>,
+    /// Days without a change after which in-progress work counts as stale
+    #[arg(long, default_value_t = 14)]
+    stale_days: u64,
+    #[command(flatten)]
+    output: OutputArgs,
+}
+
+pub async fn run(dv: &mut System, args: RiskCmd) -> Result<()> {
+    match args.action {
+        RiskAction::New(args) => new(dv, args).await,
+        RiskAction::List(args) => list(dv, args),
+        RiskAction::Close(args) => close(dv, args).await,
+    }
+}
+
+async fn new(dv: &mut System, args: NewArgs) -> Result<()> {
+    let team = match args.team {
+        Some(team) => team,
+        None => config::default_team()?,
+    };
+    let rel_dir = Risk::KIND.dir(&team);
+    let mut risk = Risk::new(&args.title);
+    risk.severity = args.severity;
+    risk.owner = Some(args.owner.unwrap_or_else(|| config::me().email.clone()));
+    risk.mitigation = args.mitigation;
+    risk.labels = args.labels;
+    match args.description {
+        Some(description) => risk.description = Some(description).filter(|d| !d.is_empty()),
+        None => {
+            let template = risk.to_doc_string_as(dv.config().doc_format(&rel_dir));
+            risk = util::edit_doc(template, |s| Risk::parse_doc(s, None))?;
+        }
+    }
+
+    let id = dv.next_doc_id::<Risk>(&team, &risk.title)?;
+    let path = dv.doc_path(&rel_dir, &id);
+    debug!("Creating risk at {}", path.display());
+    let risk = dv.create_doc(path, risk).await?.read_doc::<Risk>()?;
+    println!("Created {}", risk.id().unwrap());
+    Ok(())
+}
+
+fn list(dv: &mut System, args: ListArgs) -> Result<()> {
+    let mut risks = read_risks(dv)?;
+    risks.retain(|r| args.all || r.is_open());
+    risks.sort_by_key(|r| (r.status, std::cmp::Reverse(r.severity), r.id()));
+    for risk in risks {
+        println!(
+            "{} {:<9} {:<6} {:<24} {}",
+            format!("{:<32}", risk.id().unwrap()).bold(),
+            risk.status,
+            risk.severity,
+            risk.owner.as_deref().unwrap_or(""),
+            risk.title
+        );
+    }
+    Ok(())
+}
+
+async fn close(dv: &mut System, args: CloseArgs) -> Result<()> {
+    let Some(path) = dv.find_doc::<Risk>(&args.id) else {
+        bail!("Risk {} not found", args.id);
+    };
+    let mut risk = dv.read_doc::<Risk, _>(&path)?;
+    risk.status = match args.mitigated {
+        true => RiskStatus::Mitigated,
+        false => RiskStatus::Closed,
+    };
+    if args.mitigation.is_some() {
+        risk.mitigation = args.mitigation;
+    }
+    let status = risk.status;
+    dv.update_doc(&path, risk).await?;
+    println!("{} is {status}", args.id);
+    Ok(())
+}
+
+/// Risks of every team
+fn read_risks(dv: &System) -> Result<Vec<Risk>> {
+    let mut risks = Vec::new();
+    for team in dv.teams()? {
+        risks.extend(dv.read_docs::<Risk>(&team)?);
+    }
+    Ok(risks)
+}
+
+/// Signals that milestones and epics are at risk, with the risks recorded for them
+#[derive(Serialize)]
+struct Assessment {
+    generated: DateTime<Utc>,
+    stale_days: u64,
+    scopes: Vec<ScopeRisk>,
+    /// Open risks that aren't scoped to a milestone or epic
+    risks: Vec<RiskRef>,
+}
+
+#[derive(Serialize)]
+struct ScopeRisk {
+    /// Milestone or epic label
+    scope: String,
+    open: usize,
+    overdue: Vec<TaskRef>,
+    stale: Vec<TaskRef>,
+    blocked: Vec<TaskRef>,
+    unassigned_high_priority: Vec<TaskRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope_growth: Option<ScopeGrowth>,
+    risks: Vec<RiskRef>,
+}
+
+#[derive(Serialize)]
+struct TaskRef {
+    id: String,
+    title: String,
+    detail: String,
+}
+
+#[derive(Serialize)]
+struct RiskRef {
+    id: String,
+    title: String,
+    severity: RiskLevel,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mitigation: Option<String>,
+}
+
+/// Tasks carrying the label at the end of the day it was first used, and now
+#[derive(Serialize)]
+struct ScopeGrowth {
+    since: NaiveDate,
+    initial: usize,
+    current: usize,
+}
+
+impl TaskRef {
+    fn new(task: &Task, detail: String) -> TaskRef {
+        TaskRef {
+            id: task.id().unwrap(),
+            title: task.title.clone(),
+            detail,
+        }
+    }
+}
+
+impl From<&Risk> for RiskRef {
+    fn from(risk: &Risk) -> RiskRef {
+        RiskRef {
+            id: risk.id().unwrap(),
+            title: risk.title.clone(),
+            severity: risk.severity,
+            owner: risk.owner.clone(),
+            mitigation: risk.mitigation.clone(),
+        }
+    }
+}
+
+impl ScopeGrowth {
+    fn added(&self) -> usize {
+        self.current.saturating_sub(self.initial)
+    }
+}
+
+impl ScopeRisk {
+    fn signals(&self) -> usize {
+        self.overdue.len()
+            + self.stale.len()
+            + self.blocked.len()
+            + self.unassigned_high_priority.len()
+            + self.scope_growth.as_ref().map_or(0, |g| g.added().min(1))
+            + self.risks.len()
+    }
+
+    fn table(&self) -> Table {
+        let mut table = Table::new(&["Signal", "ID", "Title", "Detail"]);
+        let groups = [
+            ("Overdue", &self.overdue),
+            ("Stale", &self.stale),
+            ("Blocked", &self.blocked),
+            ("Unassigned", &self.unassigned_high_priority),
+        ];
+        for (signal, tasks) in groups {
+            for task in tasks {
+                table.rows.push(vec![
+                    signal.to_owned(),
+                    task.id.clone(),
+                    task.title.clone(),
+                    task.detail.clone(),
+                ]);
+            }
+        }
+        if let Some(growth) = self.scope_growth.as_ref().filter(|g| g.added() > 0) {
+            table.rows.push(vec![
+                "Scope growth".to_owned(),
+                String::new(),
+                String::new(),
+                format!(
+                    "{} → {} tasks since {}",
+                    growth.initial, growth.current, growth.since
+                ),
+            ]);
+        }
+        for risk in &self.risks {
+            table.rows.push(vec![
+                format!("Risk ({})", risk.severity),
+                risk.id.clone(),
+                risk.title.clone(),
+                risk_detail(risk),
+            ]);
+        }
+        table
+    }
+}
+
+fn risk_detail(risk: &RiskRef) -> String {
+    let owner = risk.owner.as_deref().unwrap_or("no owner");
+    match &risk.mitigation {
+        Some(mitigation) => format!("{owner}: {mitigation}"),
+        None => format!("{owner}, not mitigated"),
+    }
+}
+
+pub async fn assess(dv: &mut System, args: AssessRiskCmd) -> Result<()> {
+    let teams = report::teams_or_all(dv, &args.teams)?;
+    let mut history = Vec::new();
+    for team in &teams {
+        history.extend(report::load_history(dv, team)?);
+    }
+    let open: Vec<&Task> = history
+        .iter()
+        .filter_map(TaskHistory::current)
+        .filter(|t| is_open(t))
+        .collect();
+    let scopes: BTreeSet<String> = match &args.scope {
+        Some(scope) => BTreeSet::from([scope.clone()]),
+        None => open
+            .iter()
+            .flat_map(|t| &t.labels)
+            .filter(|l| l.starts_with(MILESTONE_PREFIX) || l.starts_with(EPIC_PREFIX))
+            .cloned()
+            .collect(),
+    };
+
+    let today = Local::now().date_naive();
+    let stale_before = Utc::now() - Days::new(args.stale_days);
+    let proposed: BTreeSet<String> = dv
+        .query::<Decision>(&format!("status = '{}'", DecisionStatus::Proposed))
+        .await?
+        .iter()
+        .filter_map(Decision::id)
+        .collect();
+    let open_by_id: BTreeMap<String, &Task> = open.iter().map(|t| (t.id().unwrap(), *t)).collect();
+    let risks: Vec<Risk> = read_risks(dv)?.into_iter().filter(Risk::is_open).collect();
+
+    let mut assessed = Vec::new();
+    for scope in scopes {
+        let tasks: Vec<&Task> = open
+            .iter()
+            .copied()
+            .filter(|t| has_label(t, &scope))
+            .collect();
+        let mut stale = Vec::new();
+        for task in tasks
+            .iter()
+            .filter(|t| report::status(t) == Status::InProgress)
+        {
+            let meta =
+                dv.read_doc_with_meta::<Task, _>(util::task_path(dv, &task.id().unwrap())?)?;
+            let updated = meta.updated.as_ref().unwrap_or(&meta.created).timestamp;
+            if updated < stale_before {
+                let days = (Utc::now() - updated).num_days();
+                stale.push(TaskRef::new(task, format!("no change in {days} days")));
+            }
+        }
+        assessed.push(ScopeRisk {
+            open: tasks.len(),
+            overdue: tasks
+                .iter()
+                .filter_map(|t| t.due.filter(|due| *due < today).map(|due| (t, due)))
+                .map(|(t, due)| TaskRef::new(t, format!("due {due}")))
+                .collect(),
+            stale,
+            blocked: tasks
+                .iter()
+                .filter_map(|t| {
+                    let chain = blocker_chain(t, &open_by_id, &proposed);
+                    (!chain.is_empty()).then(|| TaskRef::new(t, format!("← {}", chain.join(" ← "))))
+                })
+                .collect(),
+            unassigned_high_priority: tasks
+                .iter()
+                .filter(|t| t.assignee.is_none() && t.priority >= Priority::High)
+                .map(|t| TaskRef::new(t, t.priority.to_string()))
+                .collect(),
+            scope_growth: scope_growth(&history, &scope),
+            risks: risks
+                .iter()
+                .filter(|r| r.labels.contains(&scope))
+                .map(RiskRef::from)
+                .collect(),
+            scope,
+        });
+    }
+    assessed.sort_by_key(|s| std::cmp::Reverse(s.signals()));
+
+    let assessment = Assessment {
+        generated: Utc::now(),
+        stale_days: args.stale_days,
+        scopes: assessed,
+        risks: match &args.scope {
+            Some(_) => Vec::new(),
+            None => risks
+                .iter()
+                .filter(|r| {
+                    !r.labels
+                        .iter()
+                        .any(|l| l.starts_with(MILESTONE_PREFIX) || l.starts_with(EPIC_PREFIX))
+                })
+                .map(RiskRef::from)
+                .collect(),
+        },
+    };
+    args.output.write_report(
+        &assessment,
+        || assessment.to_text(),
+        || report::html_page("Risks", &assessment.to_html()),
+    )
+}
+
+fn is_open(task: &Task) -> bool {
+    matches!(report::status(task), Status::Todo | Status::InProgress)
+}
+
+/// What a task waits on, nearest first: open tasks in its `blocked_by` prop, followed
+/// transitively, or proposed decisions it's labeled with
+fn blocker_chain(
+    task: &Task,
+    open: &BTreeMap<String, &Task>,
+    proposed: &BTreeSet<String>,
+) -> Vec<String> {
+    let mut chain: Vec<String> = Vec::new();
+    let mut current = task;
+    loop {
+        let decision = current
+            .labels
+            .iter()
+            .filter_map(|l| l.strip_prefix(decision::LABEL_PREFIX))
+            .find(|id| proposed.contains(*id));
+        if let Some(id) = decision {
+            chain.push(format!("decision {id}"));
+            break;
+        }
+        let next = blocked_by(current)
+            .into_iter()
+            .find_map(|id| open.get(&id).copied())
+            .filter(|next| {
+                let id = next.id().unwrap();
+                id != task.id().unwrap() && !chain.contains(&id)
+            });
+        match next {
+            Some(next) => {
+                chain.push(next.id().unwrap());
+                current = next;
+            }
+            None => break,
+        }
+    }
+    chain
+}
+
+fn blocked_by(task: &Task) -> Vec<String> {
+    match task.props.get(BLOCKED_BY_PROP) {
+        Some(serde_yaml::Value::String(id)) => vec![id.clone()],
+        Some(serde_yaml::Value::Sequence(ids)) => ids
+            .iter()
+            .filter_map(|id| id.as_str().map(str::to_owned))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Labeled tasks at the end of the day the label was first used, against now
+fn scope_growth(history: &[TaskHistory], label: &str) -> Option<ScopeGrowth> {
+    let since = first_labeled(history, label)?
+        .with_timezone(&Local)
+        .date_naive();
+    let in_scope = |task: Option<&Task>| {
+        task.is_some_and(|t| {
+            has_label(t, label)
+                && !matches!(report::status(t), Status::Canceled | Status::Duplicate)
+        })
+    };
+    let end = util::end_of_day(since);
+    Some(ScopeGrowth {
+        since,
+        initial: history.iter().filter(|h| in_scope(h.at(end))).count(),
+        current: history.iter().filter(|h| in_scope(h.current())).count(),
+    })
+}
+
+impl Assessment {
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        for scope in &self.scopes {
+            let _ = writeln!(
+                out,
+                "{}: {} open, {} signals\n",
+                scope.scope,
+                scope.open,
+                scope.signals()
+            );
+            if scope.signals() > 0 {
+                out.push_str(&left_aligned(&scope.table()));
+                out.push('\n');
+            }
+        }
+        if !self.risks.is_empty() {
+            let _ = writeln!(out, "Other risks\n");
+            for risk in &self.risks {
+                let _ = writeln!(
+                    out,
+                    "{}  {} ({}) — {}",
+                    risk.id,
+                    risk.title,
+                    risk.severity,
+                    risk_detail(risk)
+                );
+            }
+        }
+        if out.is_empty() {
+            out.push_str("No milestones or epics with open work\n");
+        }
+        out
+    }
+
+    fn to_html(&self) -> String {
+        let mut out = String::new();
+        for scope in &self.scopes {
+            let _ = writeln!(
+                out,
+                "<h2>{} ({} open, {} signals)</h2>",
+                chart::escape(&scope.scope),
+                scope.open,
+                scope.signals()
+            );
+            out.push_str(&scope.table().to_html());
+        }
+        if !self.risks.is_empty() {
+            let _ = writeln!(out, "<h2>Other risks</h2>");
+            let mut table = Table::new(&["ID", "Title", "Severity", "Detail"]);
+            for risk in &self.risks {
+                table.rows.push(vec![
+                    risk.id.clone(),
+                    risk.title.clone(),
+                    risk.severity.to_string(),
+                    risk_detail(risk),
+                ]);
+            }
+            out.push_str(&table.to_html());
+        }
+        out
+    }
+}
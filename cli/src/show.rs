@@ -1,12 +1,12 @@
-use crate::print::print_task;
-use crate::{util, View};
+use crate::field::Field;
+use crate::print::{print_task, TaskRow};
+use crate::{template, util, View};
 use anyhow::{bail, Result};
 use clap::Args;
 use divvee::task::Task;
 use divvee::*;
 use log::debug;
 use std::io;
-use std::path::Path;
 
 #[derive(Args, Debug)]
 pub struct ShowCmd {
@@ -14,18 +14,32 @@ pub struct ShowCmd {
 
     #[arg(long, short = 'v', default_value_t=View::Detail)]
     view: View,
+
+    /// Fields to include in tabular or structured views (e.g. id,title,created)
+    #[arg(long, short = 'c', value_delimiter = ',')]
+    columns: Vec<Field>,
+
+    /// Template string, template file, or name of a template in the repo's templates/ dir
+    #[arg(long, short = 'f')]
+    format: Option<String>,
 }
 
 pub fn run(dv: &mut System, args: ShowCmd) -> Result<()> {
-    let (team, id) = util::team_and_id(&args.id)?;
-    let rel_dir = Path::new(&team).join("tasks");
-    debug!("show rel_dir: {}", rel_dir.display());
-
-    let path = rel_dir.join(&id).with_extension("md");
+    let path = util::task_path(dv, &args.id)?;
+    debug!("show path: {}", path.display());
     match dv.read_doc_with_meta::<Task, _>(path) {
-        Ok(task) => print_task(&task, args.view),
+        Ok(task) => {
+            let row = TaskRow::from(task);
+            match &args.format {
+                Some(format) => {
+                    let source = template::resolve(dv.root(), format)?;
+                    print!("{}", template::render_tasks(&source, &[row])?);
+                }
+                None => print_task(&row, args.view, &args.columns),
+            }
+        }
         Err(divvee::Error::IoError(err)) if err.kind() == io::ErrorKind::NotFound => {
-            bail!("{} not found", id);
+            bail!("{} not found", args.id);
         }
         Err(err) => {
             return Err(err.into());
@@ -0,0 +1,101 @@
+use crate::print::TaskRow;
+use anyhow::Result;
+use divvee::repo::ChangeInfo;
+use divvee::task::Task;
+use log::debug;
+use minijinja::Environment;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+const TEMPLATES_DIR: &str = "templates";
+
+/// Serde model exposed to templates: the task fields plus its history metadata
+#[derive(Serialize)]
+struct TaskModel<'a> {
+    #[serde(flatten)]
+    task: &'a Task,
+    created: Option<&'a ChangeInfo>,
+    updated: Option<&'a ChangeInfo>,
+}
+
+impl<'a> From<&'a TaskRow> for TaskModel<'a> {
+    fn from(row: &'a TaskRow) -> TaskModel<'a> {
+        TaskModel {
+            task: &row.task,
+            created: row.created(),
+            updated: row.updated(),
+        }
+    }
+}
+
+/// Resolves a `--format` argument to template source
+///
+/// Looks for a file named `format` (with any extension) in the repo's `templates/` dir,
+/// then for a file at the given path, and otherwise treats `format` as the template itself.
+pub fn resolve(root: &Path, format: &str) -> Result<String> {
+    if let Some(source) = find(root, format)? {
+        return Ok(source);
+    }
+
+    let path = Path::new(format);
+    if path.is_file() {
+        debug!("Using template {}", path.display());
+        return Ok(fs::read_to_string(path)?);
+    }
+    Ok(format.to_owned())
+}
+
+/// Source of a template named `name` (with any extension) in the repo's `templates/` dir
+pub fn find(root: &Path, name: &str) -> Result<Option<String>> {
+    let dir = root.join(TEMPLATES_DIR);
+    if let Ok(read_dir) = fs::read_dir(&dir) {
+        for entry in read_dir {
+            let path = entry?.path();
+            let name_matches = path.file_name().is_some_and(|n| n == name)
+                || path.file_stem().is_some_and(|s| s == name);
+            if name_matches && path.is_file() {
+                debug!("Using template {}", path.display());
+                return Ok(Some(fs::read_to_string(path)?));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Renders tasks with a template
+///
+/// Templates that reference `tasks` are rendered once for the whole list,
+/// otherwise the template is rendered once per task with the task fields in scope.
+pub fn render_tasks(source: &str, rows: &[TaskRow]) -> Result<String> {
+    let mut env = Environment::new();
+    env.add_template("format", source)?;
+    let tmpl = env.get_template("format")?;
+
+    if tmpl.undeclared_variables(false).contains("tasks") {
+        let tasks: Vec<TaskModel> = rows.iter().map(TaskModel::from).collect();
+        let mut out = tmpl.render(minijinja::context! { tasks })?;
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        Ok(out)
+    } else {
+        let mut out = String::new();
+        for row in rows {
+            out.push_str(&tmpl.render(TaskModel::from(row))?);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+/// Renders a template once with `context` in scope
+pub fn render<S: Serialize>(source: &str, context: S) -> Result<String> {
+    let mut env = Environment::new();
+    env.add_template("format", source)?;
+    let mut out = env.get_template("format")?.render(context)?;
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    Ok(out)
+}
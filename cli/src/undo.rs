@@ -0,0 +1,143 @@
+use crate::config;
+use anyhow::{bail, Result};
+use clap::Args;
+use divvee::repo::ChangeDiff;
+use divvee::System;
+use owo_colors::OwoColorize;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+#[derive(Args, Debug)]
+pub struct UndoCmd {
+    /// Don't ask for confirmation
+    #[arg(long, short = 'y')]
+    yes: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RevertCmd {
+    /// Hash (or unique prefix) of the change to revert
+    change: String,
+    /// Don't ask for confirmation
+    #[arg(long, short = 'y')]
+    yes: bool,
+}
+
+/// Unrecords the latest change if it's ours and hasn't been pushed
+pub async fn undo(dv: &mut System, args: UndoCmd) -> Result<()> {
+    let Some(latest) = dv.latest_change()? else {
+        bail!("Nothing to undo");
+    };
+    let me = &config::me().email;
+    if !latest.authors.contains(me) {
+        bail!(
+            "The latest change was made by {}. Use `dv revert {}` instead",
+            latest.authors.join(", "),
+            latest.hash
+        );
+    }
+    if dv.is_pushed(&latest.hash)? {
+        bail!(
+            "The latest change was already pushed. Use `dv revert {}` instead",
+            latest.hash
+        );
+    }
+
+    let diff = dv.change_diff(&latest.hash)?;
+    print_diff(&diff);
+    if !args.yes && !confirm("Undo this change?")? {
+        return Ok(());
+    }
+    dv.undo(&diff.change.hash)?;
+    dv.reindex_files(&changed_paths(&diff)).await?;
+    println!("Undid {}", diff.change.hash);
+    Ok(())
+}
+
+/// Records the inverse of a change
+pub async fn revert(dv: &mut System, args: RevertCmd) -> Result<()> {
+    let diff = dv.change_diff(&args.change)?;
+    print_diff(&diff);
+    if !args.yes && !confirm("Revert this change?")? {
+        return Ok(());
+    }
+    let msg = format!("Revert \"{}\"", diff.change.message);
+    let hash = dv.revert(&diff.change.hash, &msg)?;
+    dv.reindex_files(&changed_paths(&diff)).await?;
+    println!("Reverted {} in {}", diff.change.hash, hash);
+    Ok(())
+}
+
+/// Paths of files touched by a change
+fn changed_paths(diff: &ChangeDiff) -> Vec<PathBuf> {
+    diff.files.keys().cloned().collect()
+}
+
+fn confirm(prompt: &str) -> Result<bool> {
+    print!("{prompt} [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+fn print_diff(diff: &ChangeDiff) {
+    let change = &diff.change;
+    println!("{} {}", "change".yellow(), change.hash.yellow());
+    println!("Author: {}", change.authors.join(", "));
+    println!("Date:   {}", change.timestamp.to_rfc2822());
+    println!("\n    {}\n", change.message);
+    for (path, (before, after)) in &diff.files {
+        println!("{}", path.display().bold());
+        let before: Vec<&str> = before
+            .as_deref()
+            .map(|s| s.lines().collect())
+            .unwrap_or_default();
+        let after: Vec<&str> = after
+            .as_deref()
+            .map(|s| s.lines().collect())
+            .unwrap_or_default();
+        for line in diff_lines(&before, &after) {
+            match line {
+                Line::Same => {}
+                Line::Removed(l) => println!("{}", format!("-{l}").red()),
+                Line::Added(l) => println!("{}", format!("+{l}").green()),
+            }
+        }
+        println!();
+    }
+}
+
+enum Line<'a> {
+    Same,
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Line diff from the longest common subsequence. Docs are small, so quadratic is fine.
+fn diff_lines<'a>(a: &[&'a str], b: &[&'a str]) -> Vec<Line<'a>> {
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = match a[i] == b[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push(Line::Same);
+            (i, j) = (i + 1, j + 1);
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            lines.push(Line::Added(b[j]));
+            j += 1;
+        } else {
+            lines.push(Line::Removed(a[i]));
+            i += 1;
+        }
+    }
+    lines
+}
@@ -1,5 +1,15 @@
 use crate::config;
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use divvee::collection::Collection;
+use divvee::repo::AsOf;
+use divvee::task::Task;
+use divvee::{Error, System};
+use std::path::PathBuf;
+use tempfile::Builder;
+
+/// Marks error annotations added to docs that are reopened in the editor
+const ANNOTATION: &str = "# error: ";
 
 /// Helper that extracts team from ID
 ///
@@ -14,3 +24,128 @@ pub fn team_and_id(id: &str) -> Result<(String, String)> {
         }
     }
 }
+
+/// Quotes a string as an SQL literal, escaping any quotes in it
+pub fn sql_str(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// Path of a task document relative to the repo root, in whichever format it's in
+///
+/// Falls back to the team's archive for tasks that were archived.
+pub fn task_path(dv: &System, id: &str) -> Result<PathBuf> {
+    let (team, id) = team_and_id(id)?;
+    let path = dv.doc_path(Task::KIND.dir(&team), &id);
+    match dv.root().join(&path).exists() {
+        true => Ok(path),
+        false => Ok(dv.find_archived(&id).unwrap_or(path)),
+    }
+}
+
+/// End of a local calendar day, as a UTC timestamp
+pub fn end_of_day(date: NaiveDate) -> DateTime<Utc> {
+    let end = date.and_hms_opt(23, 59, 59).unwrap();
+    Local
+        .from_local_datetime(&end)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(|| end.and_utc())
+}
+
+/// Parses a date (YYYY-MM-DD, meaning the end of that day), an RFC 3339 time, or a change hash
+pub fn parse_as_of(s: &str) -> Result<AsOf> {
+    if let Ok(date) = s.parse::<NaiveDate>() {
+        return Ok(AsOf::Time(end_of_day(date)));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(AsOf::Time(time.with_timezone(&Utc)));
+    }
+    Ok(AsOf::Change(s.to_owned()))
+}
+
+/// Parses a date argument, where "" clears the date
+pub fn parse_date(s: &str) -> Result<Option<NaiveDate>> {
+    match s.is_empty() {
+        true => Ok(None),
+        false => Ok(Some(
+            s.parse().with_context(|| format!("Invalid date {s}"))?,
+        )),
+    }
+}
+
+/// Edits a doc until it parses, reopening the editor with errors annotated
+///
+/// Saving without changes gives up, keeping the edits in a temp file.
+pub fn edit_doc<T>(doc: String, parse: impl Fn(&str) -> divvee::Result<T>) -> Result<T> {
+    let mut doc = doc;
+    let mut last_edit = None;
+    loop {
+        let edited = edit::edit_with_builder(&doc, Builder::new().suffix(".md"))?;
+        let edited = strip_annotations(&edited);
+        let err = match parse(&edited) {
+            Ok(parsed) => return Ok(parsed),
+            Err(err @ (Error::Parse(_) | Error::Invalid { .. })) => err,
+            Err(err) => return Err(err.into()),
+        };
+        if last_edit.as_ref() == Some(&edited) {
+            let (_, path) = Builder::new()
+                .prefix("dv-")
+                .suffix(".md")
+                .tempfile()?
+                .keep()?;
+            std::fs::write(&path, &edited)?;
+            bail!("{}\nYour edits were saved to {}", err, path.display());
+        }
+        eprintln!("{err}");
+        doc = annotate(&edited, &err);
+        last_edit = Some(edited);
+    }
+}
+
+fn strip_annotations(doc: &str) -> String {
+    let mut stripped: String = doc
+        .split_inclusive('\n')
+        .filter(|line| !line.starts_with(ANNOTATION))
+        .collect();
+    if !doc.ends_with('\n') && stripped.ends_with('\n') {
+        stripped.pop();
+    }
+    stripped
+}
+
+/// Adds a comment below each line with an error, or at the top if the line isn't known
+fn annotate(doc: &str, err: &Error) -> String {
+    let errors: Vec<(Option<usize>, String)> = match err {
+        Error::Parse(err) => {
+            let message = match &err.field {
+                Some(field) => format!("{field}: {}", err.message),
+                None => err.message.clone(),
+            };
+            vec![(err.line, message)]
+        }
+        Error::Invalid { violations, .. } => violations
+            .iter()
+            .map(|v| (v.line, format!("{} {}", v.field, v.message)))
+            .collect(),
+        err => vec![(None, err.to_string())],
+    };
+    let mut lines: Vec<String> = doc.lines().map(str::to_owned).collect();
+    let top = match lines.first().is_some_and(|l| l.trim_end() == "---") {
+        true => 1,
+        false => 0,
+    };
+    let mut errors: Vec<(usize, String)> = errors
+        .into_iter()
+        .map(|(line, message)| (line.unwrap_or(top).min(lines.len()), message))
+        .collect();
+    // insert from the bottom up so earlier line numbers stay put
+    errors.sort_by_key(|(line, _)| std::cmp::Reverse(*line));
+    for (line, message) in errors {
+        lines.insert(line, format!("{ANNOTATION}{message}"));
+    }
+    let mut annotated = lines.join("\n");
+    if doc.ends_with('\n') {
+        annotated.push('\n');
+    }
+    annotated
+}
@@ -0,0 +1,184 @@
+use crate::{config, util, FilterArgs};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Days, Local, NaiveDate, Utc};
+use clap::Args;
+use divvee::decision::{Decision, LABEL_PREFIX};
+use divvee::repo::ChangeInfo;
+use divvee::task::Task;
+use divvee::watch::WatchList;
+use divvee::{Document, System};
+use owo_colors::OwoColorize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How far back the inbox looks the first time it's read
+const FIRST_READ_DAYS: u64 = 7;
+
+#[derive(Args, Debug)]
+pub struct WatchCmd {
+    /// Tasks to watch. Lists watched tasks if none are given
+    ids: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct UnwatchCmd {
+    /// Tasks to stop watching
+    #[arg(required = true)]
+    ids: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct InboxCmd {
+    /// Show changes since this date instead of since the inbox was last read
+    #[arg(long)]
+    since: Option<NaiveDate>,
+    /// Don't mark the inbox as read
+    #[arg(long)]
+    peek: bool,
+}
+
+pub async fn watch(dv: &mut System, args: WatchCmd) -> Result<()> {
+    let me = &config::me().email;
+    let mut list = WatchList::load(dv.root(), me)?;
+    if args.ids.is_empty() {
+        if list.tasks.is_empty() {
+            println!("Not watching any tasks");
+        }
+        for id in &list.tasks {
+            println!("{id}");
+        }
+        return Ok(());
+    }
+    for id in args.ids {
+        let (_, id) = util::team_and_id(&id)?;
+        if !dv.root().join(util::task_path(dv, &id)?).exists() {
+            bail!("{} not found", id);
+        }
+        if list.tasks.insert(id.clone()) {
+            println!("Watching {id}");
+        }
+    }
+    list.save(dv.root(), me)?;
+    Ok(())
+}
+
+pub async fn unwatch(dv: &mut System, args: UnwatchCmd) -> Result<()> {
+    let me = &config::me().email;
+    let mut list = WatchList::load(dv.root(), me)?;
+    for id in args.ids {
+        let (_, id) = util::team_and_id(&id)?;
+        match list.tasks.remove(&id) {
+            true => println!("Stopped watching {id}"),
+            false => println!("Not watching {id}"),
+        }
+    }
+    list.save(dv.root(), me)?;
+    Ok(())
+}
+
+/// Changes by others to watched and assigned tasks since the inbox was last read
+pub async fn inbox(dv: &mut System, args: InboxCmd) -> Result<()> {
+    let me = &config::me().email;
+    let mut list = WatchList::load(dv.root(), me)?;
+    let since: DateTime<Utc> = match (args.since, list.last_read) {
+        (Some(date), _) => util::end_of_day(date - Days::new(1)),
+        (None, Some(last_read)) => last_read,
+        (None, None) => Utc::now() - Days::new(FIRST_READ_DAYS),
+    };
+
+    let filters = FilterArgs {
+        assignee: Some(me.clone()),
+        ..FilterArgs::default()
+    };
+    let assigned: BTreeSet<String> = dv
+        .query::<Task>(&filters.to_where_clause())
+        .await?
+        .into_iter()
+        .filter_map(|t| t.id())
+        .collect();
+
+    // newest change per task decides the order tasks are shown in
+    let mut updates: BTreeMap<String, (Task, Vec<ChangeInfo>)> = BTreeMap::new();
+    for id in list.tasks.iter().chain(&assigned) {
+        if updates.contains_key(id) {
+            continue;
+        }
+        let path = util::task_path(dv, id)?;
+        if !dv.root().join(&path).exists() {
+            continue;
+        }
+        let doc = dv.load(&path)?;
+        let task: Task = doc.read_doc()?;
+        let mut changes = changes_by_others(&doc, since, me)?;
+        // decisions the task waits on, e.g. `d-adopt-postgres`
+        for decision_id in task
+            .labels
+            .iter()
+            .filter_map(|l| l.strip_prefix(LABEL_PREFIX))
+        {
+            let Some(path) = dv.find_doc::<Decision>(decision_id) else {
+                continue;
+            };
+            for mut change in changes_by_others(&dv.load(&path)?, since, me)? {
+                change.message = format!("decision {decision_id}: {}", change.message);
+                changes.push(change);
+            }
+        }
+        changes.sort_by_key(|change| std::cmp::Reverse(change.timestamp));
+        if !changes.is_empty() {
+            updates.insert(id.clone(), (task, changes));
+        }
+    }
+
+    if updates.is_empty() {
+        println!(
+            "No changes since {}",
+            since.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        );
+    }
+    let mut updates: Vec<_> = updates.into_iter().collect();
+    updates.sort_by_key(|(_, (_, changes))| std::cmp::Reverse(changes[0].timestamp));
+    for (id, (task, changes)) in updates {
+        let reason = match assigned.contains(&id) {
+            true => "assigned",
+            false => "watching",
+        };
+        println!(
+            "{} {} {}",
+            id.bold(),
+            task.title.bold(),
+            format!("({reason})").dimmed()
+        );
+        for change in changes {
+            println!(
+                "  {}  {}  {}",
+                change
+                    .timestamp
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M"),
+                change.authors.join(", "),
+                change.message
+            );
+        }
+    }
+
+    if !args.peek {
+        list.last_read = Some(Utc::now());
+        list.save(dv.root(), me)?;
+    }
+    Ok(())
+}
+
+/// Changes to a doc after `since`, newest first, that `me` didn't author
+fn changes_by_others(doc: &Document, since: DateTime<Utc>, me: &str) -> Result<Vec<ChangeInfo>> {
+    let mut changes = Vec::new();
+    for change in doc.changes()? {
+        let change = change?;
+        if change.timestamp <= since {
+            break;
+        }
+        if !change.authors.iter().any(|author| author == me) {
+            changes.push(change);
+        }
+    }
+    Ok(changes)
+}
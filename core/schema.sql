@@ -1,7 +1,71 @@
 CREATE TABLE tasks (
     id TEXT NOT NULL PRIMARY KEY,
     title TEXT,
+    type TEXT,
     status TEXT,
     assignee TEXT,
+    priority INTEGER NOT NULL DEFAULT 0,
+    rank INTEGER,
+    start DATE,
+    due DATE,
+    -- due date from the repo's SLA rules, for tasks without an explicit due date
+    sla_due DATE,
+    description TEXT
+);
+
+CREATE INDEX tasks_priority ON tasks (priority DESC, rank);
+CREATE INDEX tasks_due ON tasks (due);
+CREATE INDEX tasks_type ON tasks (type);
+
+-- One row per prop value (per item for lists), so props can be filtered and sorted
+CREATE TABLE task_props (
+    task_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT,
+    number REAL
+);
+
+CREATE INDEX task_props_task ON task_props (task_id);
+CREATE INDEX task_props_value ON task_props (name, value);
+CREATE INDEX task_props_number ON task_props (name, number);
+
+-- One row per task label, e.g. `d-adopt-postgres`
+CREATE TABLE task_labels (
+    task_id TEXT NOT NULL,
+    label TEXT NOT NULL
+);
+
+CREATE INDEX task_labels_task ON task_labels (task_id);
+CREATE INDEX task_labels_label ON task_labels (label);
+
+CREATE TABLE decisions (
+    id TEXT NOT NULL PRIMARY KEY,
+    title TEXT,
+    status TEXT NOT NULL DEFAULT 'proposed',
+    owner TEXT,
+    decided DATE,
+    outcome TEXT,
+    superseded_by TEXT,
+    context TEXT
+);
+
+CREATE INDEX decisions_status ON decisions (status);
+
+CREATE TABLE goals (
+    id TEXT NOT NULL PRIMARY KEY,
+    title TEXT,
+    status TEXT NOT NULL DEFAULT 'active',
+    owner TEXT,
+    period TEXT,
+    description TEXT
+);
+
+CREATE TABLE risks (
+    id TEXT NOT NULL PRIMARY KEY,
+    title TEXT,
+    status TEXT NOT NULL DEFAULT 'open',
+    severity TEXT NOT NULL DEFAULT 'medium',
+    owner TEXT,
+    mitigation TEXT,
     description TEXT
 );
@@ -0,0 +1,198 @@
+//! Kinds of docs, each kept in a directory of every team
+//!
+//! A kind ties a `RepoDoc + DbRecord` type to where its docs live and how they're named, so
+//! `System` can create, read, reindex and query any of them the same way.
+
+use crate::config::RepoConfig;
+use crate::db::DbRecord;
+use crate::decision::Decision;
+use crate::goal::Goal;
+use crate::risk::Risk;
+use crate::task::Task;
+use crate::{is_template, RepoDoc, Result, System};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+/// Directory in each team that closed tasks are moved to by period, e.g. `DIV/archive/2025-Q3`
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// Prefix of the labels that archived tasks get, e.g. `a-2025-Q3`
+pub const ARCHIVE_PREFIX: &str = "a-";
+
+/// File in a team's archive dir holding the highest archived task number, so the numbers
+/// stay taken without scanning the archive
+pub const LAST_ID_FILE: &str = "last-id";
+
+/// How the docs of a kind are named
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdScheme {
+    /// Numbered per team, e.g. `DIV-12`
+    Numbered,
+    /// Derived from the title, e.g. `adopt-postgres`, so IDs read well in labels
+    Slug,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Kind {
+    /// Directory under each team dir, e.g. `tasks`
+    pub dir: &'static str,
+    pub ids: IdScheme,
+    /// Indexes docs of the kind by path, i.e. `System::reindex_paths` for its type
+    pub reindex: Reindex,
+    /// Parses a doc of the kind and checks it against the repo config
+    pub validate: Validate,
+}
+
+/// Reindexes the docs at some paths, removing the ones that no longer exist
+pub type Reindex =
+    for<'a> fn(&'a System, &'a [PathBuf]) -> Pin<Box<dyn Future<Output = Result<()>> + 'a>>;
+
+/// Parses and checks a doc, i.e. `RepoDoc::parse_valid` for its type
+pub type Validate = fn(&str, &Path, &RepoConfig) -> Result<()>;
+
+// a dir holds a single kind, and fn pointers don't compare reliably
+impl PartialEq for Kind {
+    fn eq(&self, other: &Kind) -> bool {
+        self.dir == other.dir
+    }
+}
+
+impl Eq for Kind {}
+
+/// A doc type that is kept as a collection in each team
+pub trait Collection: RepoDoc + DbRecord {
+    const KIND: Kind;
+}
+
+impl Collection for Task {
+    const KIND: Kind = Kind {
+        dir: "tasks",
+        ids: IdScheme::Numbered,
+        reindex: reindex::<Task>,
+        validate: validate::<Task>,
+    };
+}
+
+impl Collection for Decision {
+    const KIND: Kind = Kind {
+        dir: "decisions",
+        ids: IdScheme::Slug,
+        reindex: reindex::<Decision>,
+        validate: validate::<Decision>,
+    };
+}
+
+impl Collection for Goal {
+    const KIND: Kind = Kind {
+        dir: "goals",
+        ids: IdScheme::Slug,
+        reindex: reindex::<Goal>,
+        validate: validate::<Goal>,
+    };
+}
+
+impl Collection for Risk {
+    const KIND: Kind = Kind {
+        dir: "risks",
+        ids: IdScheme::Slug,
+        reindex: reindex::<Risk>,
+        validate: validate::<Risk>,
+    };
+}
+
+fn reindex<'a, D: Collection + 'static>(
+    dv: &'a System,
+    paths: &'a [PathBuf],
+) -> Pin<Box<dyn Future<Output = Result<()>> + 'a>> {
+    Box::pin(dv.reindex_paths::<D>(paths))
+}
+
+fn validate<D: Collection>(s: &str, path: &Path, config: &RepoConfig) -> Result<()> {
+    D::parse_valid(s, path, config).map(|_| ())
+}
+
+/// Every kind of doc, so tools can tell which collection a file belongs to
+pub const KINDS: &[Kind] = &[Task::KIND, Decision::KIND, Goal::KIND, Risk::KIND];
+
+impl Kind {
+    /// Directory of the collection in a team, e.g. `DIV/tasks`
+    pub fn dir(&self, team: &str) -> PathBuf {
+        Path::new(team).join(self.dir)
+    }
+
+    /// Kind and team of a doc path like `DIV/tasks/DIV-1.md`
+    ///
+    /// Archived tasks like `DIV/archive/2025-Q3/DIV-1.md` are tasks too. Templates and
+    /// files outside of a collection dir have no kind.
+    pub fn of(path: &Path) -> Option<(&'static Kind, String)> {
+        let mut components = path.iter().map(|c| c.to_string_lossy());
+        let (team, dir, stem) = (components.next()?, components.next()?, path.file_stem()?);
+        let (kind, depth) = match dir == ARCHIVE_DIR {
+            true => (KINDS.iter().find(|kind| **kind == Task::KIND)?, 2),
+            false => (KINDS.iter().find(|kind| kind.dir == dir)?, 1),
+        };
+        if components.count() != depth || is_template(path) {
+            return None;
+        }
+        let named_right = match kind.ids {
+            IdScheme::Numbered => kind.number(&team, &stem.to_string_lossy()).is_some(),
+            IdScheme::Slug => true,
+        };
+        named_right.then(|| (kind, team.into_owned()))
+    }
+
+    /// Number of a numbered ID, e.g. 12 for `DIV-12` in team `DIV`
+    pub fn number(&self, team: &str, id: &str) -> Option<u32> {
+        match self.ids {
+            IdScheme::Numbered => id.strip_prefix(team)?.strip_prefix('-')?.parse().ok(),
+            IdScheme::Slug => None,
+        }
+    }
+}
+
+/// Archive dir of a team, e.g. `DIV/archive`
+pub fn archive_dir(team: &str) -> PathBuf {
+    Path::new(team).join(ARCHIVE_DIR)
+}
+
+/// Lowercase words of a title joined by dashes, e.g. `adopt-postgres` for "Adopt Postgres!"
+pub fn slugify(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_kind_of() {
+        let (kind, team) = Kind::of(Path::new("DIV/tasks/DIV-12.md")).unwrap();
+        assert_eq!((kind, team.as_str()), (&Task::KIND, "DIV"));
+        assert_eq!(kind.number("DIV", "DIV-12"), Some(12));
+        assert!(Kind::of(Path::new("DIV/tasks/OPS-1.md")).is_none());
+        assert!(Kind::of(Path::new("DIV/tasks/_bug.md")).is_none());
+        assert!(Kind::of(Path::new("DIV/notes/DIV-1.md")).is_none());
+        let (kind, _) = Kind::of(Path::new("DIV/decisions/adopt-postgres.md")).unwrap();
+        assert_eq!(kind, &Decision::KIND);
+        let (kind, _) = Kind::of(Path::new("DIV/archive/2025-Q3/DIV-1.md")).unwrap();
+        assert_eq!(kind, &Task::KIND);
+        assert!(Kind::of(Path::new("DIV/archive/DIV-1.md")).is_none());
+        assert!(Kind::of(Path::new("DIV/archive/last-id")).is_none());
+        assert_eq!(slugify("Adopt Postgres (v16)!"), "adopt-postgres-v16");
+    }
+
+    #[test]
+    fn test_kind_validate() {
+        let config = RepoConfig::default();
+        let path = Path::new("DIV/decisions/adopt-postgres.md");
+        let validate = Decision::KIND.validate;
+        assert!(validate("---\ntitle: Adopt Postgres\n---\n", path, &config).is_ok());
+        assert!(validate("---\ntitle: [\n---\n", path, &config).is_err());
+    }
+}
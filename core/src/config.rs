@@ -0,0 +1,384 @@
+use crate::format::DocFormat;
+use crate::task::Task;
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use log::debug;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Name of the repo-level config file, found at the repo root
+pub const CONFIG_FILE: &str = "divvee.toml";
+
+/// Prefix of the labels that tie tasks to a customer, e.g. `c-acme`
+pub const CUSTOMER_PREFIX: &str = "c-";
+
+/// Settings shared by everyone working in a repo
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RepoConfig {
+    pub sla: Vec<SlaRule>,
+    pub sprint: Vec<Sprint>,
+    /// Task types by name, e.g. `[types.bug]`
+    pub types: BTreeMap<String, TaskType>,
+    /// Registered task props by name, e.g. `[fields.severity]`
+    ///
+    /// Once any field is registered, unregistered props are rejected.
+    pub fields: BTreeMap<String, FieldDef>,
+    /// Per-team settings by team key, e.g. `[teams.DIV]`
+    pub teams: BTreeMap<String, TeamConfig>,
+    /// Per-person settings by email, e.g. `[people."ana@example.com"]`
+    pub people: BTreeMap<String, PersonConfig>,
+    /// Customer registry by key, e.g. `[customers.acme]` for tasks labeled `c-acme`
+    pub customers: BTreeMap<String, Customer>,
+}
+
+/// Settings for the docs of one team
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TeamConfig {
+    /// Syntax new docs are written in; existing docs keep theirs
+    pub format: DocFormat,
+    /// Estimate total of open work the team can carry. Defaults to the sum of its members'
+    pub capacity: Option<f64>,
+}
+
+/// Settings for one person
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PersonConfig {
+    /// Team the person is a member of
+    pub team: Option<String>,
+    /// Estimate total of open work the person can carry
+    pub capacity: Option<f64>,
+}
+
+/// A customer that work is done for
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Customer {
+    /// Display name, e.g. "Acme Corp"
+    pub name: Option<String>,
+    /// Support or pricing tier, e.g. `enterprise`
+    pub tier: Option<String>,
+    /// Other names the customer goes by on the command line
+    pub aliases: Vec<String>,
+}
+
+/// Type and constraints of a registered prop
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldDef {
+    #[serde(rename = "type")]
+    pub kind: FieldType,
+    /// Allowed values of an `enum`, or of the items of a `list`
+    #[serde(default)]
+    pub values: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+    /// Value given to new tasks
+    #[serde(default)]
+    pub default: Option<toml::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    String,
+    Number,
+    Date,
+    Enum,
+    /// A person, identified by email
+    User,
+    List,
+}
+
+/// Schema for tasks of one type
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TaskType {
+    /// Props that tasks of this type must set
+    pub required: Vec<String>,
+}
+
+/// Due date rule applied to tasks carrying a label
+///
+/// The label may end with `*` to match a label prefix, e.g. `c-*` for every customer.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SlaRule {
+    pub label: String,
+    pub due_within_days: u64,
+}
+
+/// Sprint label and the days it runs, inclusive
+#[derive(Debug, Clone, Deserialize)]
+pub struct Sprint {
+    pub label: String,
+    #[serde(deserialize_with = "toml_date")]
+    pub start: NaiveDate,
+    #[serde(deserialize_with = "toml_date")]
+    pub end: NaiveDate,
+}
+
+impl FieldDef {
+    /// Describes why a value doesn't fit this field, if it doesn't
+    pub fn check(&self, value: &serde_yaml::Value) -> Option<String> {
+        use serde_yaml::Value;
+        let allowed = |s: &str| self.values.is_empty() || self.values.iter().any(|v| v == s);
+        match (self.kind, value) {
+            (FieldType::String, Value::String(_)) => None,
+            (FieldType::Number, Value::Number(_)) => None,
+            (FieldType::Date, Value::String(s)) if s.parse::<NaiveDate>().is_ok() => None,
+            (FieldType::Enum, Value::String(s)) if allowed(s) => None,
+            (FieldType::User, Value::String(s)) if s.contains('@') => None,
+            (FieldType::List, Value::Sequence(items)) => items
+                .iter()
+                .find(|item| !item.as_str().is_some_and(allowed))
+                .map(|item| format!("has unexpected item {}", yaml_str(item))),
+            (FieldType::Enum, _) => Some(format!(
+                "must be one of {}, found {}",
+                self.values.join(", "),
+                yaml_str(value)
+            )),
+            (kind, _) => Some(format!(
+                "must be a {}, found {}",
+                kind.as_str(),
+                yaml_str(value)
+            )),
+        }
+    }
+
+    pub fn default_value(&self) -> Option<serde_yaml::Value> {
+        self.default.as_ref().map(yaml_value)
+    }
+}
+
+impl FieldType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldType::String => "string",
+            FieldType::Number => "number",
+            FieldType::Date => "date",
+            FieldType::Enum => "enum",
+            FieldType::User => "user",
+            FieldType::List => "list",
+        }
+    }
+}
+
+fn yaml_str(value: &serde_yaml::Value) -> String {
+    match serde_yaml::to_string(value) {
+        Ok(s) => s.trim_end().to_owned(),
+        Err(_) => format!("{value:?}"),
+    }
+}
+
+/// Converts TOML to YAML values, keeping dates as `YYYY-MM-DD` strings
+pub(crate) fn yaml_value(value: &toml::Value) -> serde_yaml::Value {
+    use serde_yaml::Value;
+    match value {
+        toml::Value::String(s) => Value::String(s.clone()),
+        toml::Value::Integer(i) => Value::Number((*i).into()),
+        toml::Value::Float(f) => Value::Number((*f).into()),
+        toml::Value::Boolean(b) => Value::Bool(*b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(items) => Value::Sequence(items.iter().map(yaml_value).collect()),
+        toml::Value::Table(table) => Value::Mapping(
+            table
+                .iter()
+                .map(|(k, v)| (Value::String(k.clone()), yaml_value(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Accepts both TOML dates (`2026-10-01`) and date strings (`"2026-10-01"`)
+fn toml_date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+    let value = toml::Value::deserialize(deserializer)?;
+    let s = match value {
+        toml::Value::Datetime(dt) => dt.to_string(),
+        toml::Value::String(s) => s,
+        other => return Err(D::Error::custom(format!("expected a date, found {other}"))),
+    };
+    s.parse().map_err(D::Error::custom)
+}
+
+impl RepoConfig {
+    pub fn load(root: &Path) -> crate::Result<RepoConfig> {
+        let path = root.join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(RepoConfig::default());
+        }
+        debug!("Loading repo config from {}", path.display());
+        let config = toml::from_str(&fs::read_to_string(path)?)?;
+        Ok(config)
+    }
+
+    /// Format for new docs at `path`, which is configured by the team dir it's under
+    pub fn doc_format(&self, path: &Path) -> DocFormat {
+        path.components()
+            .next()
+            .and_then(|team| self.teams.get(&*team.as_os_str().to_string_lossy()))
+            .map(|team| team.format)
+            .unwrap_or_default()
+    }
+
+    /// Capacity of a team, configured or summed up from its members
+    pub fn team_capacity(&self, team: &str) -> Option<f64> {
+        if let Some(capacity) = self.teams.get(team).and_then(|t| t.capacity) {
+            return Some(capacity);
+        }
+        self.people
+            .values()
+            .filter(|person| person.team.as_deref() == Some(team))
+            .filter_map(|person| person.capacity)
+            .reduce(|a, b| a + b)
+    }
+
+    /// Registry key of a customer given by key, display name or alias, ignoring case
+    pub fn customer_key(&self, name: &str) -> Option<&str> {
+        let name = name.strip_prefix(CUSTOMER_PREFIX).unwrap_or(name);
+        self.customers
+            .iter()
+            .find(|(key, customer)| {
+                key.eq_ignore_ascii_case(name)
+                    || customer
+                        .name
+                        .iter()
+                        .chain(&customer.aliases)
+                        .any(|n| n.eq_ignore_ascii_case(name))
+            })
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// Earliest SLA due date for a task created at `created`, if any rule applies
+    pub fn sla_due(&self, task: &Task, created: DateTime<Utc>) -> Option<NaiveDate> {
+        self.sla
+            .iter()
+            .filter(|rule| task.labels.iter().any(|label| rule.matches(label)))
+            .filter_map(|rule| {
+                created
+                    .date_naive()
+                    .checked_add_days(Days::new(rule.due_within_days))
+            })
+            .min()
+    }
+
+    /// Sprint running on `date`, if any
+    pub fn current_sprint(&self, date: NaiveDate) -> Option<&Sprint> {
+        self.sprint
+            .iter()
+            .find(|sprint| sprint.start <= date && date <= sprint.end)
+    }
+}
+
+impl SlaRule {
+    pub fn matches(&self, label: &str) -> bool {
+        match self.label.strip_suffix('*') {
+            Some(prefix) => label.to_lowercase().starts_with(&prefix.to_lowercase()),
+            None => label.eq_ignore_ascii_case(&self.label),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sla_due() {
+        let config: RepoConfig = toml::from_str(
+            "[[sla]]\nlabel = \"c-*\"\ndue_within_days = 5\n\n\
+             [[sla]]\nlabel = \"c-acme\"\ndue_within_days = 3\n",
+        )
+        .unwrap();
+        let created = "2026-10-01T12:00:00Z".parse().unwrap();
+
+        let mut task = Task::new("hello");
+        assert_eq!(config.sla_due(&task, created), None);
+        task.labels = vec!["c-globex".into()];
+        assert_eq!(
+            config.sla_due(&task, created),
+            NaiveDate::from_ymd_opt(2026, 10, 6)
+        );
+        task.labels = vec!["C-acme".into()];
+        assert_eq!(
+            config.sla_due(&task, created),
+            NaiveDate::from_ymd_opt(2026, 10, 4)
+        );
+    }
+
+    #[test]
+    fn test_current_sprint() {
+        let config: RepoConfig = toml::from_str(
+            "[[sprint]]\nlabel = \"s-alpha\"\nstart = 2026-10-01\nend = 2026-10-14\n\n\
+             [[sprint]]\nlabel = \"s-beta\"\nstart = 2026-10-15\nend = 2026-10-28\n",
+        )
+        .unwrap();
+        let sprint = |d| config.current_sprint(d).map(|s| s.label.as_str());
+        assert_eq!(
+            sprint(NaiveDate::from_ymd_opt(2026, 10, 14).unwrap()),
+            Some("s-alpha")
+        );
+        assert_eq!(
+            sprint(NaiveDate::from_ymd_opt(2026, 10, 15).unwrap()),
+            Some("s-beta")
+        );
+        assert_eq!(sprint(NaiveDate::from_ymd_opt(2026, 11, 1).unwrap()), None);
+    }
+
+    #[test]
+    fn test_team_capacity() {
+        let config: RepoConfig = toml::from_str(
+            "[teams.OPS]\ncapacity = 20\n\n\
+             [people.\"ana@example.com\"]\nteam = \"DIV\"\ncapacity = 8\n\n\
+             [people.\"bo@example.com\"]\nteam = \"DIV\"\ncapacity = 5.5\n",
+        )
+        .unwrap();
+        assert_eq!(config.team_capacity("OPS"), Some(20.0));
+        assert_eq!(config.team_capacity("DIV"), Some(13.5));
+        assert_eq!(config.team_capacity("WEB"), None);
+    }
+
+    #[test]
+    fn test_customer_key() {
+        let config: RepoConfig = toml::from_str(
+            "[customers.acme]\nname = \"Acme Corp\"\ntier = \"enterprise\"\naliases = [\"ACME Inc\"]\n",
+        )
+        .unwrap();
+        assert_eq!(config.customer_key("acme"), Some("acme"));
+        assert_eq!(config.customer_key("c-Acme"), Some("acme"));
+        assert_eq!(config.customer_key("acme corp"), Some("acme"));
+        assert_eq!(config.customer_key("ACME Inc"), Some("acme"));
+        assert_eq!(config.customer_key("globex"), None);
+    }
+
+    #[test]
+    fn test_field_check() {
+        let config: RepoConfig = toml::from_str(
+            "[fields.severity]\ntype = \"enum\"\nvalues = [\"S1\", \"S2\"]\ndefault = \"S2\"\n\n\
+             [fields.estimate]\ntype = \"number\"\n\n\
+             [fields.found]\ntype = \"date\"\ndefault = 2026-10-01\n",
+        )
+        .unwrap();
+        let check = |name: &str, yaml: &str| {
+            config.fields[name].check(&serde_yaml::from_str(yaml).unwrap())
+        };
+        assert_eq!(check("severity", "S1"), None);
+        assert_eq!(
+            check("severity", "S9").unwrap(),
+            "must be one of S1, S2, found S9"
+        );
+        assert_eq!(check("estimate", "3.5"), None);
+        assert_eq!(
+            check("estimate", "lots").unwrap(),
+            "must be a number, found lots"
+        );
+        assert_eq!(check("found", "2026-10-01"), None);
+        assert!(check("found", "soon").is_some());
+        assert_eq!(
+            config.fields["found"].default_value(),
+            Some(serde_yaml::Value::String("2026-10-01".into()))
+        );
+    }
+}
@@ -1,6 +1,10 @@
+use crate::decision::Decision;
+use crate::goal::Goal;
+use crate::risk::Risk;
 use crate::task::Task;
 use crate::Result;
 use log::debug;
+use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::{SqliteConnection, SqlitePool};
 
 pub struct Db(SqlitePool);
@@ -19,26 +23,179 @@ impl DbRecord for Task {
     async fn upsert_record(&self, conn: &mut SqliteConnection) -> Result<()> {
         let id = self.id().unwrap();
         sqlx::query!(
-            "insert or replace into tasks (id, title, status, assignee, description) values (?, ?, ?, ?, ?)",
+            "insert or replace into tasks (id, title, type, status, assignee, priority, rank, start, due, sla_due, description) values (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             id,
             self.title,
+            self.kind,
             self.status,
             self.assignee,
+            self.priority,
+            self.rank,
+            self.start,
+            self.due,
+            self.sla_due,
             self.description,
         )
-        .execute(conn)
+        .execute(&mut *conn)
         .await?;
+
+        sqlx::query!("delete from task_labels where task_id = ?", id)
+            .execute(&mut *conn)
+            .await?;
+        for label in &self.labels {
+            sqlx::query!(
+                "insert into task_labels (task_id, label) values (?, ?)",
+                id,
+                label
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        sqlx::query!("delete from task_props where task_id = ?", id)
+            .execute(&mut *conn)
+            .await?;
+        for (name, value) in &self.props {
+            let values = match value {
+                serde_yaml::Value::Sequence(items) => items.iter().collect(),
+                value => vec![value],
+            };
+            for value in values.into_iter().filter_map(prop_value) {
+                let number = value.parse::<f64>().ok();
+                sqlx::query!(
+                    "insert into task_props (task_id, name, value, number) values (?, ?, ?, ?)",
+                    id,
+                    name,
+                    value,
+                    number,
+                )
+                .execute(&mut *conn)
+                .await?;
+            }
+        }
         Ok(())
     }
     async fn delete_record(conn: &mut SqliteConnection, id: &str) -> Result<()> {
         sqlx::query!("delete from tasks where id = ?", id)
             .execute(&mut *conn)
             .await?;
+        sqlx::query!("delete from task_props where task_id = ?", id)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query!("delete from task_labels where task_id = ?", id)
+            .execute(&mut *conn)
+            .await?;
         Ok(())
     }
     async fn query_dangerous(conn: &mut SqliteConnection, where_clause: &str) -> Result<Vec<Self>> {
         let sql = format!("select * from tasks where {}", where_clause);
         debug!("query_dangerous: {sql}");
+        let mut records: Vec<Task> = sqlx::query_as(&sql).fetch_all(&mut *conn).await?;
+        for record in &mut records {
+            let id = record.id();
+            record.labels =
+                sqlx::query_scalar!("select label from task_labels where task_id = ?", id)
+                    .fetch_all(&mut *conn)
+                    .await?;
+        }
+        Ok(records)
+    }
+}
+
+impl DbRecord for Decision {
+    const TABLE: &'static str = "decisions";
+
+    async fn upsert_record(&self, conn: &mut SqliteConnection) -> Result<()> {
+        let id = self.id().unwrap();
+        sqlx::query!(
+            "insert or replace into decisions (id, title, status, owner, decided, outcome, superseded_by, context) values (?, ?, ?, ?, ?, ?, ?, ?)",
+            id,
+            self.title,
+            self.status,
+            self.owner,
+            self.decided,
+            self.outcome,
+            self.superseded_by,
+            self.context,
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+    async fn delete_record(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+        sqlx::query!("delete from decisions where id = ?", id)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+    async fn query_dangerous(conn: &mut SqliteConnection, where_clause: &str) -> Result<Vec<Self>> {
+        let sql = format!("select * from decisions where {}", where_clause);
+        debug!("query_dangerous: {sql}");
+        let records = sqlx::query_as(&sql).fetch_all(&mut *conn).await?;
+        Ok(records)
+    }
+}
+
+impl DbRecord for Goal {
+    const TABLE: &'static str = "goals";
+
+    async fn upsert_record(&self, conn: &mut SqliteConnection) -> Result<()> {
+        let id = self.id().unwrap();
+        sqlx::query!(
+            "insert or replace into goals (id, title, status, owner, period, description) values (?, ?, ?, ?, ?, ?)",
+            id,
+            self.title,
+            self.status,
+            self.owner,
+            self.period,
+            self.description,
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+    async fn delete_record(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+        sqlx::query!("delete from goals where id = ?", id)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+    async fn query_dangerous(conn: &mut SqliteConnection, where_clause: &str) -> Result<Vec<Self>> {
+        let sql = format!("select * from goals where {}", where_clause);
+        debug!("query_dangerous: {sql}");
+        let records = sqlx::query_as(&sql).fetch_all(&mut *conn).await?;
+        Ok(records)
+    }
+}
+
+impl DbRecord for Risk {
+    const TABLE: &'static str = "risks";
+
+    async fn upsert_record(&self, conn: &mut SqliteConnection) -> Result<()> {
+        let id = self.id().unwrap();
+        sqlx::query!(
+            "insert or replace into risks (id, title, status, severity, owner, mitigation, description) values (?, ?, ?, ?, ?, ?, ?)",
+            id,
+            self.title,
+            self.status,
+            self.severity,
+            self.owner,
+            self.mitigation,
+            self.description,
+        )
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+    async fn delete_record(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+        sqlx::query!("delete from risks where id = ?", id)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+    async fn query_dangerous(conn: &mut SqliteConnection, where_clause: &str) -> Result<Vec<Self>> {
+        let sql = format!("select * from risks where {}", where_clause);
+        debug!("query_dangerous: {sql}");
         let records = sqlx::query_as(&sql).fetch_all(&mut *conn).await?;
         Ok(records)
     }
@@ -51,6 +208,21 @@ impl Db {
         Ok(Db(pool))
     }
 
+    /// Connects to an empty in-memory DB with the schema applied
+    pub async fn memory() -> Result<Db> {
+        // each connection gets its own in-memory DB, so keep exactly one alive
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::raw_sql(include_str!("../schema.sql"))
+            .execute(&pool)
+            .await?;
+        Ok(Db(pool))
+    }
+
     pub async fn upsert_record<D: DbRecord>(&self, record: &D) -> Result<()> {
         let mut conn = self.0.acquire().await?;
         record.upsert_record(&mut *conn).await
@@ -67,3 +239,13 @@ impl Db {
         D::query_dangerous(&mut *conn, where_clause).await
     }
 }
+
+/// Indexed text of a scalar prop value
+fn prop_value(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,171 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use crate::format::{self, DocFormat, FieldsDoc};
+use crate::{error, Error, RepoDoc};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the labels that tie tasks to a decision, e.g. `d-adopt-postgres`
+pub const LABEL_PREFIX: &str = "d-";
+
+/// An architecture or product decision, in the style of an ADR
+///
+/// Tasks carrying the decision's label (`d-<id>`) are blocked while it's proposed.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, sqlx::FromRow)]
+pub struct Decision {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub status: DecisionStatus,
+    /// Who makes the call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// When the decision was accepted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decided: Option<NaiveDate>,
+    /// Options that were considered
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[sqlx(skip)]
+    pub options: Vec<String>,
+    /// What was decided, usually one of the options
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<String>,
+    /// ID of the decision that replaced this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<String>,
+    /// Background and forces at play, as the body of the doc
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum DecisionStatus {
+    #[default]
+    Proposed,
+    Accepted,
+    Superseded,
+}
+
+impl DecisionStatus {
+    pub const ALL: [DecisionStatus; 3] = [
+        DecisionStatus::Proposed,
+        DecisionStatus::Accepted,
+        DecisionStatus::Superseded,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DecisionStatus::Proposed => "proposed",
+            DecisionStatus::Accepted => "accepted",
+            DecisionStatus::Superseded => "superseded",
+        }
+    }
+}
+
+impl fmt::Display for DecisionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for DecisionStatus {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<DecisionStatus, Error> {
+        DecisionStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| error::msg(format!("Unknown decision status {s}")))
+    }
+}
+
+impl Decision {
+    pub fn new(title: &str) -> Decision {
+        Decision {
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn id(&self) -> Option<String> {
+        self.id.clone()
+    }
+
+    /// Label of the tasks this decision affects, e.g. `d-adopt-postgres`
+    pub fn label(&self) -> Option<String> {
+        self.id.as_ref().map(|id| format!("{LABEL_PREFIX}{id}"))
+    }
+
+    /// Accepts the decision as of `date`
+    pub fn accept(&mut self, outcome: Option<String>, date: NaiveDate) {
+        self.status = DecisionStatus::Accepted;
+        self.decided = Some(date);
+        if outcome.is_some() {
+            self.outcome = outcome;
+        }
+    }
+}
+
+impl FieldsDoc for Decision {
+    const BODY: &'static str = "context";
+    const KDL_LISTS: &'static [&'static str] = &["options"];
+
+    fn body(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
+    fn set_body(&mut self, body: String) {
+        self.context = Some(body);
+    }
+
+    fn set_id(&mut self, id: Option<String>) {
+        self.id = id;
+    }
+}
+
+impl RepoDoc for Decision {
+    fn parse_doc(s: &str, path: Option<PathBuf>) -> Result<Self, Error> {
+        format::parse_doc(s, path)
+    }
+
+    fn update_doc_string(&self, old: &str) -> String {
+        format::update_doc(self, old)
+    }
+
+    fn to_doc_string(&self) -> String {
+        self.to_doc_string_as(DocFormat::Yaml)
+    }
+
+    fn to_doc_string_as(&self, format: DocFormat) -> String {
+        format::write_doc(self, format)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let s = "---\ntitle: Adopt Postgres\nstatus: proposed\noptions:\n- Postgres\n- MySQL\n---\n\nWe outgrew SQLite.\n";
+        let path = PathBuf::from("DIV/decisions/adopt-postgres.md");
+        let mut decision = Decision::parse_doc(s, Some(path)).unwrap();
+        assert_eq!(decision.label().as_deref(), Some("d-adopt-postgres"));
+        assert_eq!(decision.context.as_deref(), Some("We outgrew SQLite.\n"));
+
+        let date = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        decision.accept(Some("Postgres".into()), date);
+        assert_eq!(
+            decision.update_doc_string(s),
+            "---\ntitle: Adopt Postgres\nstatus: accepted\noptions:\n- Postgres\n- MySQL\noutcome: Postgres\ndecided: 2026-10-18\n---\n\nWe outgrew SQLite.\n"
+        );
+        assert_eq!(
+            "Accepted".parse::<DecisionStatus>().unwrap(),
+            DecisionStatus::Accepted
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use std::error::Error as StdError;
 use std::fmt::{self, Debug};
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -21,12 +21,160 @@ pub enum Error {
     DeserError(Box<dyn StdError + 'static + Send + Sync>),
     // #[error("invalid header (expected {expected:?}, found {found:?})")]
     // InvalidHeader { expected: String, found: String },
+    #[error("{0}")]
+    Parse(ParseError),
+    #[error("{} is invalid: {}", .path.display(), join(.violations))]
+    Invalid {
+        path: PathBuf,
+        violations: Vec<Violation>,
+    },
     #[error("unknown error")]
     Unknown,
     #[error("{0}")]
     Msg(String),
 }
 
+/// Front matter that couldn't be read, with where it went wrong
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub path: Option<PathBuf>,
+    /// Line in the doc, counting from 1
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new(path: Option<&Path>, message: impl Into<String>) -> ParseError {
+        ParseError {
+            path: path.map(Path::to_owned),
+            line: None,
+            column: None,
+            field: None,
+            message: message.into(),
+        }
+    }
+
+    /// Converts a YAML error in front matter that starts after `line_offset` lines of the doc
+    pub fn from_yaml(
+        path: Option<&Path>,
+        err: &serde_yaml::Error,
+        line_offset: usize,
+    ) -> ParseError {
+        let mut parse_error = ParseError::new(path, err.to_string());
+        if let Some(location) = err.location() {
+            parse_error.line = Some(location.line() + line_offset);
+            parse_error.column = Some(location.column());
+            let suffix = format!(" at line {} column {}", location.line(), location.column());
+            if let Some(message) = parse_error.message.strip_suffix(&suffix) {
+                parse_error.message = message.to_owned();
+            }
+        }
+        // serde_yaml reports nested errors as `field.path: message`
+        if let Some((field, message)) = parse_error.message.split_once(": ") {
+            if !field.contains(' ') {
+                parse_error.field = Some(field.to_owned());
+                parse_error.message = message.to_owned();
+            }
+        }
+        if let Some(field) = parse_error
+            .message
+            .strip_prefix("missing field `")
+            .and_then(|rest| rest.strip_suffix('`'))
+        {
+            parse_error.field = Some(field.to_owned());
+        }
+        parse_error
+    }
+
+    /// Converts a TOML error in front matter that starts after `line_offset` lines of the doc
+    pub fn from_toml(
+        path: Option<&Path>,
+        err: &toml::de::Error,
+        matter: &str,
+        line_offset: usize,
+    ) -> ParseError {
+        let mut parse_error = ParseError::new(path, err.message());
+        if let Some(span) = err.span() {
+            parse_error.locate(matter, span.start, line_offset);
+        }
+        parse_error
+    }
+
+    /// Converts a KDL error in a whole doc
+    pub fn from_kdl(path: Option<&Path>, err: &kdl::KdlError, s: &str) -> ParseError {
+        let mut parse_error = ParseError::new(path, err.to_string());
+        if let Some(help) = err.help {
+            parse_error.message = format!("{} ({help})", parse_error.message);
+        }
+        parse_error.locate(s, err.span.offset(), 0);
+        parse_error
+    }
+
+    /// Sets the line and column of a byte offset into `s`
+    fn locate(&mut self, s: &str, offset: usize, line_offset: usize) {
+        let mut end = offset.min(s.len());
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        let before = &s[..end];
+        self.line = Some(before.matches('\n').count() + 1 + line_offset);
+        self.column = Some(before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1);
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}:", path.display())?;
+        }
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{line}:{column}: ")?,
+            (Some(line), None) => write!(f, "{line}: ")?,
+            _ if self.path.is_some() => write!(f, " ")?,
+            _ => {}
+        }
+        if let Some(field) = &self.field {
+            write!(f, "{field}: ")?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+/// A doc field that doesn't satisfy the repo config
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub field: String,
+    pub message: String,
+    /// Line of the field in the doc, counting from 1
+    pub line: Option<usize>,
+}
+
+impl Violation {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Violation {
+        Violation {
+            field: field.into(),
+            message: message.into(),
+            line: None,
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {line}: ")?;
+        }
+        write!(f, "{} {}", self.field, self.message)
+    }
+}
+
+fn join(violations: &[Violation]) -> String {
+    let violations: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+    violations.join(", ")
+}
+
 macro_rules! error_convert {
     // ($($path:ident)::+<$($generic_param:ident $( : $bounds:tt )? ),*>, $dest:expr) => {
     //     impl<$($generic_param),*> From<$($path)::*<$($generic_param),*>> for Error
@@ -54,6 +202,7 @@ macro_rules! error_convert {
 // error_convert!(libpijul::fs::FsError<T: TreeTxnT>, Error::RepoError);
 
 error_convert!(toml::de::Error, Error::DeserError);
+error_convert!(toml::ser::Error, Error::SerError);
 error_convert!(serde_json::Error, Error::DeserError);
 error_convert!(serde_yaml::Error, Error::DeserError);
 // error_convert!(
@@ -0,0 +1,481 @@
+//! Syntaxes that docs can be written in
+//!
+//! Markdown docs carry their fields in YAML (`---`) or TOML (`+++`) front matter. KDL docs
+//! (`.kdl`) are all nodes, with the description in a `description` node.
+
+use crate::config::yaml_value;
+use crate::error::ParseError;
+use crate::{matter, Error};
+use kdl::{KdlDocument, KdlEntry, KdlNode, KdlValue};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Name of the KDL node holding the body of a doc
+const BODY: &str = "description";
+
+/// Name of the KDL node holding list items, e.g. `links { - "DIV-1" }`
+const ITEM: &str = "-";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocFormat {
+    /// Markdown with `---` YAML front matter
+    #[default]
+    Yaml,
+    /// Markdown with `+++` TOML front matter
+    Toml,
+    /// A KDL document
+    Kdl,
+}
+
+impl DocFormat {
+    pub const ALL: [DocFormat; 3] = [DocFormat::Yaml, DocFormat::Toml, DocFormat::Kdl];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocFormat::Yaml => "yaml",
+            DocFormat::Toml => "toml",
+            DocFormat::Kdl => "kdl",
+        }
+    }
+
+    /// File extension of docs in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DocFormat::Yaml | DocFormat::Toml => "md",
+            DocFormat::Kdl => "kdl",
+        }
+    }
+
+    /// Format of existing doc text, going by its path and opening line
+    ///
+    /// Without a path, text that has no front matter is taken to be KDL if it parses as KDL.
+    pub fn detect(s: &str, path: Option<&Path>) -> DocFormat {
+        let first = s.lines().next().unwrap_or_default().trim_end();
+        match path.and_then(Path::extension) {
+            Some(ext) if ext == "kdl" => DocFormat::Kdl,
+            _ if first == "+++" => DocFormat::Toml,
+            None if first != "---" && s.parse::<KdlDocument>().is_ok() => DocFormat::Kdl,
+            _ => DocFormat::Yaml,
+        }
+    }
+}
+
+impl fmt::Display for DocFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses doc text in any format into its fields and body
+///
+/// The body is the markdown after the front matter, or the `description` node of a KDL doc.
+/// `lists` names fields that are always lists, for KDL docs.
+pub(crate) fn parse<T: DeserializeOwned>(
+    s: &str,
+    path: Option<&Path>,
+    lists: &[&str],
+) -> Result<(T, Option<String>), Error> {
+    let parse_error = |message: &str| Error::Parse(ParseError::new(path, message));
+    let (fields, body) = match DocFormat::detect(s, path) {
+        DocFormat::Yaml => {
+            let Some(split) = matter::split(s, matter::DELIMITER) else {
+                return Err(parse_error(
+                    "missing front matter, expected the doc to start with ---",
+                ));
+            };
+            // deserialize the raw front matter so errors can point at a line
+            let fields = serde_yaml::from_str::<T>(split.matter)
+                .map_err(|err| Error::Parse(ParseError::from_yaml(path, &err, 1)))?;
+            (fields, Some(split.body))
+        }
+        DocFormat::Toml => {
+            let Some(split) = matter::split(s, matter::TOML_DELIMITER) else {
+                return Err(parse_error("front matter is missing its closing +++"));
+            };
+            let table = toml::from_str::<toml::Table>(split.matter)
+                .map_err(|err| Error::Parse(ParseError::from_toml(path, &err, split.matter, 1)))?;
+            let fields = serde_yaml::from_value::<T>(yaml_value(&toml::Value::Table(table)))
+                .map_err(|err| Error::Parse(ParseError::from_yaml(path, &err, 1)))?;
+            (fields, Some(split.body))
+        }
+        DocFormat::Kdl => {
+            let doc = s
+                .parse::<KdlDocument>()
+                .map_err(|err| Error::Parse(ParseError::from_kdl(path, &err, s)))?;
+            let mut fields = kdl_fields(&doc, lists);
+            let body = fields.remove(BODY);
+            let body = body.as_ref().and_then(Value::as_str).map(str::to_owned);
+            let fields = serde_yaml::from_value::<T>(Value::Mapping(fields))
+                .map_err(|err| Error::Parse(ParseError::from_yaml(path, &err, 0)))?;
+            return Ok((fields, body));
+        }
+    };
+    let body = body
+        .map(|body| body.trim_start_matches('\n'))
+        .filter(|body| !body.is_empty())
+        .map(str::to_owned);
+    Ok((fields, body))
+}
+
+/// Serializes a new doc's fields and body in a format
+pub(crate) fn write(
+    fields: &Mapping,
+    body: Option<&str>,
+    format: DocFormat,
+    lists: &[&str],
+) -> String {
+    let body = body.unwrap_or("");
+    match format {
+        DocFormat::Yaml => {
+            let yaml = serde_yaml::to_string(fields).unwrap_or_default();
+            format!("---\n{}\n---\n\n{}", yaml, body)
+        }
+        DocFormat::Toml => format!("+++\n{}+++\n\n{}", matter::to_toml(fields), body),
+        DocFormat::Kdl => to_kdl(&with_body(fields, Some(body)), lists),
+    }
+}
+
+/// Applies changed fields and body to a doc's text, in the format the text is in
+///
+/// Keeps comments, unknown fields and formatting. Returns `None` if `old` can't be edited.
+pub(crate) fn update(
+    old: &str,
+    (old_fields, old_body): (&Mapping, Option<&str>),
+    (new_fields, new_body): (&Mapping, Option<&str>),
+    lists: &[&str],
+) -> Option<String> {
+    let body = match old_body == new_body {
+        true => None,
+        false => Some(format!("\n{}", new_body.unwrap_or(""))),
+    };
+    match DocFormat::detect(old, None) {
+        DocFormat::Yaml => matter::merge(old, old_fields, new_fields, body.as_deref()),
+        DocFormat::Toml => matter::merge_toml(old, old_fields, new_fields, body.as_deref()),
+        DocFormat::Kdl => merge_kdl(
+            old,
+            &with_body(old_fields, old_body),
+            &with_body(new_fields, new_body),
+            lists,
+        ),
+    }
+}
+
+/// Fields of a KDL doc, where the body is a node like any other
+fn with_body(fields: &Mapping, body: Option<&str>) -> Mapping {
+    let mut fields = fields.clone();
+    if let Some(body) = body.filter(|body| !body.is_empty()) {
+        fields.insert(BODY.into(), body.into());
+    }
+    fields
+}
+
+/// Serialized fields of a doc, leaving out `skip`, e.g. the ID and body
+fn fields_of<T: Serialize>(doc: &T, skip: &[&str]) -> Mapping {
+    match serde_yaml::to_value(doc) {
+        Ok(Value::Mapping(mut fields)) => {
+            for key in skip {
+                fields.remove(*key);
+            }
+            fields
+        }
+        _ => Mapping::new(),
+    }
+}
+
+/// A doc made of serialized fields around a markdown body, which can be in any format
+///
+/// Kinds implement it to get their `RepoDoc` text handling from `parse_doc`, `update_doc`
+/// and `write_doc`.
+pub(crate) trait FieldsDoc: Serialize + DeserializeOwned {
+    /// Field holding the body, e.g. `description`
+    const BODY: &'static str;
+    /// Fields that are lists even when a KDL node gives a single value
+    const KDL_LISTS: &'static [&'static str];
+
+    fn body(&self) -> Option<&str>;
+    fn set_body(&mut self, body: String);
+    /// Sets the ID, which comes from the file name rather than the doc's fields
+    fn set_id(&mut self, id: Option<String>);
+}
+
+/// Parses a doc, taking its ID from the file name in `path`
+pub(crate) fn parse_doc<T: FieldsDoc>(s: &str, path: Option<PathBuf>) -> Result<T, Error> {
+    let (mut doc, body): (T, _) = parse(s, path.as_deref(), T::KDL_LISTS)?;
+    if let Some(body) = body {
+        doc.set_body(body);
+    }
+    doc.set_id(path.and_then(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned())));
+    Ok(doc)
+}
+
+/// Writes a doc over its previous text, keeping what it doesn't model
+pub(crate) fn update_doc<T: FieldsDoc>(doc: &T, old: &str) -> String {
+    let Ok(old_doc) = parse_doc::<T>(old, None) else {
+        return write_doc(doc, DocFormat::Yaml);
+    };
+    update(
+        old,
+        (&doc_fields_of(&old_doc), old_doc.body()),
+        (&doc_fields_of(doc), doc.body()),
+        T::KDL_LISTS,
+    )
+    .unwrap_or_else(|| write_doc(doc, DocFormat::detect(old, None)))
+}
+
+/// Serializes a new doc in a format
+pub(crate) fn write_doc<T: FieldsDoc>(doc: &T, format: DocFormat) -> String {
+    write(&doc_fields_of(doc), doc.body(), format, T::KDL_LISTS)
+}
+
+fn doc_fields_of<T: FieldsDoc>(doc: &T) -> Mapping {
+    fields_of(doc, &["id", T::BODY])
+}
+
+/// Fields of doc text in any format, without reading them as a particular doc type
+///
+/// `lists` names fields that are always lists, for KDL docs. Returns `None` if the text
+/// can't be parsed.
+pub fn doc_fields(s: &str, path: Option<&Path>, lists: &[&str]) -> Option<Mapping> {
+    match DocFormat::detect(s, path) {
+        DocFormat::Yaml => serde_yaml::from_str(matter::split(s, matter::DELIMITER)?.matter).ok(),
+        DocFormat::Toml => {
+            let table = toml::from_str(matter::split(s, matter::TOML_DELIMITER)?.matter).ok()?;
+            match yaml_value(&toml::Value::Table(table)) {
+                Value::Mapping(fields) => Some(fields),
+                _ => None,
+            }
+        }
+        DocFormat::Kdl => Some(kdl_fields(&s.parse().ok()?, lists)),
+    }
+}
+
+/// Reads top-level nodes as fields
+///
+/// `lists` names fields that are always lists, even when given a single value.
+pub(crate) fn kdl_fields(doc: &KdlDocument, lists: &[&str]) -> Mapping {
+    doc.nodes()
+        .iter()
+        .map(|node| {
+            let name = node.name().value();
+            let value = match (node_value(node), lists.contains(&name)) {
+                (Value::Null, true) => Value::Sequence(Vec::new()),
+                (value @ Value::Sequence(_), true) => value,
+                (value, true) => Value::Sequence(vec![value]),
+                (value, false) => value,
+            };
+            (Value::String(name.to_owned()), value)
+        })
+        .collect()
+}
+
+/// A node's value: its argument, a list of arguments or `-` children, or a mapping of children
+fn node_value(node: &KdlNode) -> Value {
+    if let Some(children) = node.children() {
+        let nodes = children.nodes();
+        return match nodes.iter().all(|n| n.name().value() == ITEM) && !nodes.is_empty() {
+            true => Value::Sequence(nodes.iter().map(node_value).collect()),
+            false => Value::Mapping(kdl_fields(children, &[])),
+        };
+    }
+    let mut args: Vec<Value> = node
+        .entries()
+        .iter()
+        .filter(|e| e.name().is_none())
+        .map(|e| yaml_scalar(e.value()))
+        .collect();
+    match args.len() {
+        0 => Value::Null,
+        1 => args.remove(0),
+        _ => Value::Sequence(args),
+    }
+}
+
+fn yaml_scalar(value: &KdlValue) -> Value {
+    match value {
+        KdlValue::RawString(s) | KdlValue::String(s) => Value::String(s.clone()),
+        KdlValue::Base2(i) | KdlValue::Base8(i) | KdlValue::Base10(i) | KdlValue::Base16(i) => {
+            Value::Number((*i).into())
+        }
+        KdlValue::Base10Float(f) => Value::Number((*f).into()),
+        KdlValue::Bool(b) => Value::Bool(*b),
+        KdlValue::Null => Value::Null,
+    }
+}
+
+fn kdl_scalar(value: &Value) -> Option<KdlValue> {
+    match value {
+        // multi-line text such as the description reads best as a raw string
+        Value::String(s) if s.contains('\n') => Some(KdlValue::RawString(s.clone())),
+        Value::String(s) => Some(KdlValue::String(s.clone())),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Some(KdlValue::Base10(i)),
+            None => n.as_f64().map(KdlValue::Base10Float),
+        },
+        Value::Bool(b) => Some(KdlValue::Bool(*b)),
+        Value::Null => Some(KdlValue::Null),
+        _ => None,
+    }
+}
+
+/// Builds the node for a field, the inverse of `node_value`
+fn field_node(name: &str, value: &Value, is_list: bool) -> KdlNode {
+    let mut node = KdlNode::new(name);
+    match value {
+        Value::Sequence(items) => {
+            let scalars: Option<Vec<KdlValue>> = items.iter().map(kdl_scalar).collect();
+            match scalars {
+                // a single argument would read back as a plain value
+                Some(args) if items.len() > 1 || is_list => {
+                    for arg in args {
+                        node.push(KdlEntry::new(arg));
+                    }
+                }
+                _ => {
+                    let children = node.ensure_children();
+                    for item in items {
+                        children.nodes_mut().push(field_node(ITEM, item, false));
+                    }
+                }
+            }
+        }
+        Value::Mapping(fields) => {
+            let children = node.ensure_children();
+            for (key, value) in fields {
+                if let Some(key) = key.as_str() {
+                    children.nodes_mut().push(field_node(key, value, false));
+                }
+            }
+        }
+        Value::Tagged(tagged) => return field_node(name, &tagged.value, is_list),
+        Value::Null => {}
+        scalar => {
+            if let Some(arg) = kdl_scalar(scalar) {
+                node.push(KdlEntry::new(arg));
+            }
+        }
+    }
+    node
+}
+
+/// Serializes fields as a KDL document, one node per field
+pub(crate) fn to_kdl(fields: &Mapping, lists: &[&str]) -> String {
+    let mut doc = KdlDocument::new();
+    for (key, value) in fields {
+        if let Some(key) = key.as_str() {
+            doc.nodes_mut()
+                .push(field_node(key, value, lists.contains(&key)));
+        }
+    }
+    doc.to_string()
+}
+
+/// Applies changed fields to a KDL doc, keeping comments and unknown nodes
+///
+/// Like `matter::merge`, fields in neither `old_fields` nor `new_fields` are kept as they are.
+/// Returns `None` if `old` isn't valid KDL.
+pub(crate) fn merge_kdl(
+    old: &str,
+    old_fields: &Mapping,
+    new_fields: &Mapping,
+    lists: &[&str],
+) -> Option<String> {
+    let mut doc: KdlDocument = old.parse().ok()?;
+    merge_nodes(&mut doc, old_fields, new_fields, lists);
+    Some(doc.to_string())
+}
+
+fn merge_nodes(doc: &mut KdlDocument, old_fields: &Mapping, new_fields: &Mapping, lists: &[&str]) {
+    let keys: Vec<&Value> = old_fields.keys().chain(new_fields.keys()).collect();
+    for key in keys {
+        let Some(name) = key.as_str() else { continue };
+        let (old, new) = (old_fields.get(key), new_fields.get(key));
+        if old == new {
+            continue;
+        }
+        let index = doc.nodes().iter().position(|n| n.name().value() == name);
+        match (index, old, new) {
+            (Some(i), Some(Value::Mapping(old)), Some(Value::Mapping(new)))
+                if doc.nodes()[i].children().is_some() =>
+            {
+                if let Some(children) = doc.nodes_mut()[i].children_mut() {
+                    merge_nodes(children, old, new, &[]);
+                }
+            }
+            (Some(i), _, Some(new)) => {
+                let old_node = &doc.nodes()[i];
+                let mut node = field_node(name, new, lists.contains(&name));
+                if let Some(leading) = old_node.leading() {
+                    node.set_leading(leading);
+                }
+                if let Some(trailing) = old_node.trailing() {
+                    node.set_trailing(trailing);
+                }
+                doc.nodes_mut()[i] = node;
+            }
+            (Some(i), _, None) => {
+                doc.nodes_mut().remove(i);
+            }
+            (None, _, Some(new)) => {
+                // new fields go ahead of the description, which reads best last
+                let at = doc
+                    .nodes()
+                    .iter()
+                    .position(|n| n.name().value() == "description")
+                    .unwrap_or(doc.nodes().len());
+                let mut node = field_node(name, new, lists.contains(&name));
+                if let Some(leading) = doc.nodes().get(at).and_then(KdlNode::leading) {
+                    // keep the indentation of the doc
+                    let indent = leading.rsplit('\n').next().unwrap_or_default();
+                    node.set_leading(indent);
+                }
+                doc.nodes_mut().insert(at, node);
+            }
+            (None, _, None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let md = Path::new("DIV/tasks/DIV-1.md");
+        let kdl = Path::new("DIV/tasks/DIV-1.kdl");
+        assert_eq!(
+            DocFormat::detect("---\ntitle: a\n---", Some(md)),
+            DocFormat::Yaml
+        );
+        assert_eq!(
+            DocFormat::detect("+++\ntitle = \"a\"\n+++", Some(md)),
+            DocFormat::Toml
+        );
+        assert_eq!(DocFormat::detect("title \"a\"", Some(kdl)), DocFormat::Kdl);
+        assert_eq!(DocFormat::detect("title \"a\"", None), DocFormat::Kdl);
+        // not front matter, but not KDL either
+        assert_eq!(DocFormat::detect("# Notes\n", Some(md)), DocFormat::Yaml);
+    }
+
+    #[test]
+    fn test_kdl_fields() {
+        let doc: KdlDocument = "title \"a\"\nlabels \"bug\"\nprops {\n    links \"DIV-1\" \"DIV-2\"\n    one {\n        - \"DIV-3\"\n    }\n    estimate 3\n}\n"
+            .parse()
+            .unwrap();
+        let fields = kdl_fields(&doc, &["labels"]);
+        let expected: Mapping = serde_yaml::from_str(
+            "title: a\nlabels: [bug]\nprops:\n  links: [DIV-1, DIV-2]\n  one: [DIV-3]\n  estimate: 3\n",
+        )
+        .unwrap();
+        assert_eq!(fields, expected);
+
+        // and back again
+        let s = to_kdl(&fields, &["labels"]);
+        let doc: KdlDocument = s.parse().unwrap();
+        assert_eq!(kdl_fields(&doc, &["labels"]), expected);
+    }
+}
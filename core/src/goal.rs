@@ -0,0 +1,235 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use crate::format::{self, DocFormat, FieldsDoc};
+use crate::{error, Error, RepoDoc};
+use serde::{Deserialize, Serialize, Serializer};
+
+/// Prefix of the labels that align tasks with a goal, e.g. `g-grow-self-serve`
+pub const LABEL_PREFIX: &str = "g-";
+
+/// An objective with measurable key results
+///
+/// Work is aligned with a goal by labeling tasks `g-<id>`, or by listing the epics
+/// (`e-` labels) that serve it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, sqlx::FromRow)]
+pub struct Goal {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub status: GoalStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// When the goal is meant to be met, e.g. `2026-Q4`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[sqlx(skip)]
+    pub key_results: Vec<KeyResult>,
+    /// Epic labels aligned as a whole, e.g. `e-onboarding`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[sqlx(skip)]
+    pub epics: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum GoalStatus {
+    #[default]
+    Active,
+    Achieved,
+    Dropped,
+}
+
+/// A target metric, e.g. 500 weekly active teams
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct KeyResult {
+    pub name: String,
+    #[serde(serialize_with = "whole_number")]
+    pub target: f64,
+    #[serde(
+        default,
+        serialize_with = "whole_number",
+        skip_serializing_if = "is_zero"
+    )]
+    pub current: f64,
+}
+
+fn is_zero(n: &f64) -> bool {
+    *n == 0.0
+}
+
+/// Writes whole numbers without a fraction, so `500` isn't rewritten as `500.0`
+fn whole_number<S: Serializer>(n: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+    match n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        true => serializer.serialize_i64(*n as i64),
+        false => serializer.serialize_f64(*n),
+    }
+}
+
+impl GoalStatus {
+    pub const ALL: [GoalStatus; 3] = [
+        GoalStatus::Active,
+        GoalStatus::Achieved,
+        GoalStatus::Dropped,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GoalStatus::Active => "active",
+            GoalStatus::Achieved => "achieved",
+            GoalStatus::Dropped => "dropped",
+        }
+    }
+}
+
+impl fmt::Display for GoalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for GoalStatus {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<GoalStatus, Error> {
+        GoalStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| error::msg(format!("Unknown goal status {s}")))
+    }
+}
+
+impl KeyResult {
+    /// Share of the target reached, from 0 to 1
+    pub fn progress(&self) -> f64 {
+        match self.target == 0.0 {
+            true => 1.0,
+            false => (self.current / self.target).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Parses `NAME=TARGET`, e.g. `weekly active teams=500`
+impl FromStr for KeyResult {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<KeyResult, Error> {
+        let invalid = || error::msg(format!("Expected NAME=TARGET, got {s:?}"));
+        let (name, target) = s.rsplit_once('=').ok_or_else(invalid)?;
+        Ok(KeyResult {
+            name: name.trim().to_owned(),
+            target: target.trim().parse().map_err(|_| invalid())?,
+            current: 0.0,
+        })
+    }
+}
+
+impl Goal {
+    pub fn new(title: &str) -> Goal {
+        Goal {
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn id(&self) -> Option<String> {
+        self.id.clone()
+    }
+
+    /// Label of the tasks aligned with this goal, e.g. `g-grow-self-serve`
+    pub fn label(&self) -> Option<String> {
+        self.id.as_ref().map(|id| format!("{LABEL_PREFIX}{id}"))
+    }
+
+    /// Mean progress of the key results, if there are any
+    pub fn progress(&self) -> Option<f64> {
+        match self.key_results.is_empty() {
+            true => None,
+            false => Some(
+                self.key_results
+                    .iter()
+                    .map(KeyResult::progress)
+                    .sum::<f64>()
+                    / self.key_results.len() as f64,
+            ),
+        }
+    }
+
+    /// Key result with a name, ignoring case
+    pub fn key_result_mut(&mut self, name: &str) -> Option<&mut KeyResult> {
+        self.key_results
+            .iter_mut()
+            .find(|kr| kr.name.eq_ignore_ascii_case(name))
+    }
+}
+
+impl FieldsDoc for Goal {
+    const BODY: &'static str = "description";
+    const KDL_LISTS: &'static [&'static str] = &["epics"];
+
+    fn body(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    fn set_body(&mut self, body: String) {
+        self.description = Some(body);
+    }
+
+    fn set_id(&mut self, id: Option<String>) {
+        self.id = id;
+    }
+}
+
+impl RepoDoc for Goal {
+    fn parse_doc(s: &str, path: Option<PathBuf>) -> Result<Self, Error> {
+        format::parse_doc(s, path)
+    }
+
+    fn update_doc_string(&self, old: &str) -> String {
+        format::update_doc(self, old)
+    }
+
+    fn to_doc_string(&self) -> String {
+        self.to_doc_string_as(DocFormat::Yaml)
+    }
+
+    fn to_doc_string_as(&self, format: DocFormat) -> String {
+        format::write_doc(self, format)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_key_results() {
+        let s = "---\ntitle: Grow self-serve\nkey_results:\n- name: weekly active teams\n  target: 500\n  current: 125\n- name: churn\n  target: 0\n---\n";
+        let path = PathBuf::from("DIV/goals/grow-self-serve.md");
+        let mut goal = Goal::parse_doc(s, Some(path)).unwrap();
+        assert_eq!(goal.label().as_deref(), Some("g-grow-self-serve"));
+        assert_eq!(goal.progress(), Some(0.625));
+
+        goal.key_result_mut("Weekly Active Teams").unwrap().current = 250.0;
+        assert_eq!(
+            goal.update_doc_string(s),
+            s.replace("current: 125", "current: 250")
+        );
+
+        let kr: KeyResult = "NPS = 40".parse().unwrap();
+        assert_eq!((kr.name.as_str(), kr.target), ("NPS", 40.0));
+        assert!("NPS".parse::<KeyResult>().is_err());
+    }
+
+    #[test]
+    fn test_kdl() {
+        let s = "title \"Grow self-serve\"\nkey_results {\n    - {\n        name \"weekly active teams\"\n        target 500\n    }\n}\nepics \"e-onboarding\"\n";
+        let goal = Goal::parse_doc(s, Some(PathBuf::from("DIV/goals/grow.kdl"))).unwrap();
+        assert_eq!(goal.key_results[0].target, 500.0);
+        assert_eq!(goal.epics, vec!["e-onboarding"]);
+    }
+}
@@ -1,34 +1,113 @@
-pub use error::Error;
+pub use error::{Error, Violation};
 // use itertools::Itertools;
+use chrono::{DateTime, Utc};
 use db::{Db, DbRecord};
 use libpijul::Base32;
-use log::debug;
+use log::{debug, warn};
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::{self, Write};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
 
+pub mod collection;
+pub mod config;
 pub mod db;
+pub mod decision;
 pub mod error;
+pub mod format;
+pub mod goal;
+mod matter;
 pub mod repo;
+pub mod risk;
 pub mod task;
+pub mod watch;
 
 pub type Result<T> = std::result::Result<T, error::Error>;
-use repo::{ChangeInfo, Repository};
+
+use collection::{Collection, IdScheme, Kind, ARCHIVE_PREFIX, KINDS, LAST_ID_FILE};
+use config::RepoConfig;
+use format::DocFormat;
+use repo::{AsOf, ChangeDiff, ChangeInfo, Repository};
+use task::Task;
+
+/// Prefix of template files kept alongside docs, e.g. `DIV/tasks/_bug.md`
+pub const TEMPLATE_PREFIX: char = '_';
+
+/// Whether a path names a template rather than a doc
+pub fn is_template(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with(TEMPLATE_PREFIX))
+}
 
 pub struct System {
     repo: Repository,
     db: Db,
+    config: RepoConfig,
 }
 
 pub trait RepoDoc: Sized {
     fn to_doc_string(&self) -> String;
     fn parse_doc(s: &str, path: Option<PathBuf>) -> Result<Self>;
+
+    /// Serializes a new doc in one of the repo's formats
+    ///
+    /// Doc types with a single syntax can ignore the format.
+    fn to_doc_string_as(&self, _format: DocFormat) -> String {
+        self.to_doc_string()
+    }
+
+    /// Serializes the doc over its previous text, keeping anything the doc type doesn't model
+    fn update_doc_string(&self, _old: &str) -> String {
+        self.to_doc_string()
+    }
+
+    /// Checks the doc against the repo config before it's written
+    fn validate(&self, _config: &RepoConfig) -> Vec<Violation> {
+        Vec::new()
+    }
+
+    /// Validates the doc as written in `s`, so violations point at their line
+    fn check(&self, s: &str, path: &Path, config: &RepoConfig) -> Result<()> {
+        let mut violations = self.validate(config);
+        for violation in &mut violations {
+            violation.line = field_line(s, &violation.field);
+        }
+        violations.sort_by_key(|v| v.line.unwrap_or(usize::MAX));
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(Error::Invalid {
+                path: path.to_owned(),
+                violations,
+            }),
+        }
+    }
+
+    /// Parses a doc and checks it against the repo config
+    fn parse_valid(s: &str, path: &Path, config: &RepoConfig) -> Result<Self> {
+        let doc = Self::parse_doc(s, Some(path.to_owned()))?;
+        doc.check(s, path, config)?;
+        Ok(doc)
+    }
+
+    /// Fills in fields that are computed for the index rather than written in the doc
+    ///
+    /// `created` looks up when the doc was first recorded, so only call it when needed.
+    fn derive_fields(
+        &mut self,
+        _config: &RepoConfig,
+        _created: &dyn Fn() -> Result<DateTime<Utc>>,
+    ) -> Result<()> {
+        Ok(())
+    }
 }
 
-pub trait Doc {
-    type RepoDoc;
-    // fn augment_doc(doc: RepoDoc, authorship: TBD)
+/// A document as it was after a change
+#[derive(Debug, Clone)]
+pub struct DocRevision<T> {
+    pub change: ChangeInfo,
+    /// The parsed document, or `None` if the change deleted it
+    pub doc: Option<T>,
 }
 
 pub struct MetaDoc<T> {
@@ -53,19 +132,93 @@ impl RepoDoc for String {
     }
 }
 
+/// Line (counting from 1) of a doc field such as `type` or `props.severity`
+///
+/// Understands YAML and TOML front matter (`key:`, `key =` and `[key]` tables) as well as
+/// KDL nodes. Falls back to the closest parent that can be found.
+fn field_line(s: &str, field: &str) -> Option<usize> {
+    let lines: Vec<&str> = s.lines().collect();
+    // don't look for fields in the body
+    let end = match lines.first().map(|l| l.trim_end()) {
+        Some(open @ ("---" | "+++")) => (1..lines.len())
+            .find(|&i| lines[i].trim_end() == open)
+            .unwrap_or(lines.len()),
+        _ => lines.len(),
+    };
+    let is_key = |line: &str, key: &str| {
+        line == format!("[{key}]")
+            || line
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with([':', '=', ' ', '\t', '{']))
+    };
+    let (mut found, mut start, mut indent) = (None, 0, None);
+    for key in field.split('.') {
+        let Some(i) = (start..end).find(|&i| {
+            let line = lines[i].trim_start();
+            let line_indent = lines[i].len() - line.len();
+            is_key(line, key) && indent.is_none_or(|indent| line_indent > indent)
+        }) else {
+            break;
+        };
+        let line = lines[i].trim_start();
+        // keys of a TOML table aren't indented
+        indent = match line.starts_with('[') {
+            true => None,
+            false => Some(lines[i].len() - line.len()),
+        };
+        (found, start) = (Some(i + 1), i + 1);
+    }
+    found
+}
+
+/// A task that passed its checks and is ready to move into the archive
+struct ArchiveMove {
+    id: String,
+    from: PathBuf,
+    to: PathBuf,
+    /// Contents of the archived doc, with its archive label
+    doc: String,
+}
+
 impl System {
     pub async fn init<P: AsRef<Path>>(repo_dir: P) -> Result<System> {
         let p = repo_dir.as_ref();
         let repo = Repository::find_root(Some(p.into()))?;
         let db = Db::connect(&p.join(".db.sqlite").to_string_lossy()).await?;
-        Ok(System { repo, db })
+        let config = RepoConfig::load(&repo.path)?;
+        Ok(System { repo, db, config })
+    }
+
+    /// Returns the repo-level config
+    pub fn config(&self) -> &RepoConfig {
+        &self.config
+    }
+
+    /// Returns the root directory of the document repo
+    pub fn root(&self) -> &Path {
+        &self.repo.path
+    }
+
+    /// Team dirs in the repo, i.e. root dirs that hold a collection
+    pub fn teams(&self) -> Result<Vec<String>> {
+        let mut teams: Vec<String> = fs::read_dir(&self.repo.path)?
+            .filter_map(|e| e.ok())
+            .filter(|e| KINDS.iter().any(|kind| e.path().join(kind.dir).is_dir()))
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        teams.sort();
+        Ok(teams)
     }
 
     pub fn next_id<P: AsRef<Path>>(&self, dir: &P) -> Result<u32> {
         let path = self.repo.path.join(dir);
         debug!("Looking up next_id in {}", path.display());
+        if !path.exists() {
+            return Ok(1);
+        }
         let last = fs::read_dir(path)?
             .filter_map(|res| res.map(|e| e.path()).ok())
+            .filter(|fpath| !is_template(fpath))
             .filter_map(|fpath| {
                 fpath
                     .file_stem()
@@ -89,16 +242,212 @@ impl System {
             return Err(error::io_error(io::ErrorKind::AlreadyExists, path));
         }
 
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(self.repo.path.join(dir))?;
+        }
+        // new docs are written in their team's format
+        let s = new_doc.to_doc_string_as(self.config.doc_format(path));
+        new_doc.check(&s, path, &self.config)?;
         let mut doc = Document::new(self.repo.clone(), path)?;
-        doc.write(&new_doc, true)?;
+        doc.write_string(&s, true)?;
 
         // TODO: change this to read the full type for upsert
         let record = doc.read_doc::<D>()?;
-        self.db.upsert_record(&record).await?;
+        self.upsert(path, record).await?;
 
         Ok(doc)
     }
 
+    /// ID for a new doc in a team's collection
+    ///
+    /// Slugs get a numbered suffix if they're taken, e.g. `adopt-postgres-2`.
+    pub fn next_doc_id<D: Collection>(&self, team: &str, title: &str) -> Result<String> {
+        match D::KIND.ids {
+            IdScheme::Numbered => {
+                let next = self.next_id(&D::KIND.dir(team))?;
+                // archived numbers stay taken
+                let next = match D::KIND == Task::KIND {
+                    true => next.max(self.last_archived_id(team)? + 1),
+                    false => next,
+                };
+                Ok(format!("{team}-{next}"))
+            }
+            IdScheme::Slug => {
+                let slug = collection::slugify(title);
+                if slug.is_empty() {
+                    return Err(error::msg(format!("Can't make an ID from {title:?}")));
+                }
+                let id = (1..)
+                    .map(|n| match n {
+                        1 => slug.clone(),
+                        n => format!("{slug}-{n}"),
+                    })
+                    .find(|id| self.find_doc::<D>(id).is_none())
+                    .unwrap();
+                Ok(id)
+            }
+        }
+    }
+
+    /// Highest number of the team's archived tasks, or 0 if none were archived
+    pub fn last_archived_id(&self, team: &str) -> Result<u32> {
+        let path = self
+            .repo
+            .path
+            .join(collection::archive_dir(team))
+            .join(LAST_ID_FILE);
+        match fs::read_to_string(&path) {
+            Ok(s) => s
+                .trim()
+                .parse()
+                .map_err(|_| error::msg(format!("Expected a number in {}", path.display()))),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Archived task docs of a team, e.g. `DIV/archive/2025-Q3/DIV-1.md`
+    pub fn archived_paths(&self, team: &str) -> Result<Vec<PathBuf>> {
+        let dir = collection::archive_dir(team);
+        if !self.repo.path.join(&dir).is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut paths = Vec::new();
+        for period in fs::read_dir(self.repo.path.join(&dir))? {
+            let period = period?;
+            if !period.path().is_dir() {
+                continue;
+            }
+            let period_dir = dir.join(period.file_name());
+            paths.extend(
+                fs::read_dir(period.path())?
+                    .filter_map(|e| e.ok())
+                    .map(|e| period_dir.join(e.file_name()))
+                    .filter(|path| {
+                        Kind::of(path).is_some_and(|(k, t)| *k == Task::KIND && t == team)
+                    }),
+            );
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Path of an archived task
+    pub fn find_archived(&self, id: &str) -> Option<PathBuf> {
+        let team = id.split('-').next()?;
+        self.archived_paths(team)
+            .ok()?
+            .into_iter()
+            .find(|path| path.file_stem().is_some_and(|stem| stem == id))
+    }
+
+    /// Moves closed tasks to the team's archive in one change, returning their new paths
+    /// along with the IDs of tasks that were left in place and why
+    ///
+    /// Each task goes to the archive dir of its period (e.g. `DIV/archive/2025-Q3`) and gets
+    /// the period's archive label, e.g. `a-2025-Q3`. Every task is checked before any is
+    /// moved, so an invalid one is skipped instead of leaving the archive half done.
+    pub async fn archive_tasks(
+        &self,
+        team: &str,
+        tasks: &[(String, String)],
+    ) -> Result<(Vec<PathBuf>, Vec<(String, Error)>)> {
+        let (moves, skipped) = self.prepare_archive(team, tasks);
+        let mut repo = self.repo.clone();
+        let archive_dir = collection::archive_dir(team);
+        let mut last_id = self.last_archived_id(team)?;
+        let mut archived = Vec::new();
+        for ArchiveMove { id, from, to, doc } in moves {
+            repo.move_file(&from, &to)?;
+            fs::write(self.repo.path.join(&to), doc)?;
+            last_id = last_id.max(Task::KIND.number(team, &id).unwrap_or(0));
+            archived.push(to);
+        }
+        if archived.is_empty() {
+            return Ok((archived, skipped));
+        }
+
+        let last_id_path = archive_dir.join(LAST_ID_FILE);
+        fs::write(self.repo.path.join(&last_id_path), format!("{last_id}\n"))?;
+        repo.add_file(&last_id_path)?;
+        let noun = match archived.len() {
+            1 => "task",
+            _ => "tasks",
+        };
+        repo.record(&format!("Archived {} {noun} in {team}", archived.len()))?;
+        for path in &archived {
+            self.reindex::<Task>(path).await?;
+        }
+        Ok((archived, skipped))
+    }
+
+    /// Reads, relabels and checks tasks to archive, splitting off the ones that fail
+    fn prepare_archive(
+        &self,
+        team: &str,
+        tasks: &[(String, String)],
+    ) -> (Vec<ArchiveMove>, Vec<(String, Error)>) {
+        let archive_dir = collection::archive_dir(team);
+        let mut moves = Vec::new();
+        let mut skipped = Vec::new();
+        for (id, period) in tasks {
+            let from = self.doc_path(Task::KIND.dir(team), id);
+            let Some(file_name) = from.file_name() else {
+                continue;
+            };
+            let to = archive_dir.join(period).join(file_name);
+            let doc = fs::read_to_string(self.repo.path.join(&from))
+                .map_err(Error::from)
+                .and_then(|old| {
+                    let mut task = Task::parse_doc(&old, Some(from.clone()))?;
+                    let label = format!("{ARCHIVE_PREFIX}{period}");
+                    if !task.labels.contains(&label) {
+                        task.labels.push(label);
+                    }
+                    let new = task.update_doc_string(&old);
+                    task.check(&new, &to, &self.config)?;
+                    Ok(new)
+                });
+            match doc {
+                Ok(doc) => moves.push(ArchiveMove {
+                    id: id.clone(),
+                    from,
+                    to,
+                    doc,
+                }),
+                Err(err) => skipped.push((id.clone(), err)),
+            }
+        }
+        (moves, skipped)
+    }
+
+    /// Path of an existing doc of a kind, looking in every team for slug IDs
+    pub fn find_doc<D: Collection>(&self, id: &str) -> Option<PathBuf> {
+        let teams = match D::KIND.ids {
+            IdScheme::Numbered => vec![id.split('-').next()?.to_owned()],
+            IdScheme::Slug => self.teams().ok()?,
+        };
+        teams
+            .iter()
+            .map(|team| self.doc_path(D::KIND.dir(team), id))
+            .find(|path| self.repo.path.join(path).exists())
+    }
+
+    /// Path of a doc in `dir`, in whichever format it exists in
+    ///
+    /// Falls back to the format new docs in `dir` are written in.
+    pub fn doc_path<P: AsRef<Path>>(&self, dir: P, id: &str) -> PathBuf {
+        let dir = dir.as_ref();
+        DocFormat::ALL
+            .iter()
+            .map(|format| dir.join(id).with_extension(format.extension()))
+            .find(|path| self.repo.path.join(path).exists())
+            .unwrap_or_else(|| {
+                let format = self.config.doc_format(&dir.join(id));
+                dir.join(id).with_extension(format.extension())
+            })
+    }
+
     pub async fn update_doc<D: RepoDoc + DbRecord, P: AsRef<Path>>(
         &self,
         path: P,
@@ -110,11 +459,67 @@ impl System {
         }
 
         let mut doc = Document::new(self.repo.clone(), path)?;
-        doc.write(&new_doc, true)?;
+        // check the text as it will be written, so violations point at the right lines
+        let s = new_doc.update_doc_string(&doc.read_to_string()?);
+        new_doc.check(&s, path, &self.config)?;
+        doc.write_string(&s, true)?;
 
         // TODO: change this to read the full type for upsert
         let record = doc.read_doc::<D>()?;
-        self.db.upsert_record(&record).await?;
+        self.upsert(path, record).await?;
+
+        Ok(doc)
+    }
+
+    /// Updates several docs in one change, so they're applied or rejected together
+    ///
+    /// Every doc is checked before any is written.
+    pub async fn update_docs<D: RepoDoc + DbRecord>(
+        &self,
+        updates: Vec<(PathBuf, D)>,
+        msg: &str,
+    ) -> Result<()> {
+        let mut writes = Vec::new();
+        for (path, new_doc) in &updates {
+            if !self.repo.path.join(path).exists() {
+                return Err(error::io_error(io::ErrorKind::NotFound, path));
+            }
+            let doc = Document::new(self.repo.clone(), path)?;
+            let s = new_doc.update_doc_string(&doc.read_to_string()?);
+            new_doc.check(&s, path, &self.config)?;
+            writes.push((doc, s));
+        }
+        if writes.is_empty() {
+            return Ok(());
+        }
+
+        let mut repo = self.repo.clone();
+        for (doc, s) in &mut writes {
+            doc.write_string(s, false)?;
+            repo.add_file(doc.repo_path())?;
+        }
+        repo.record(msg)?;
+        for (doc, _) in &writes {
+            self.upsert(doc.repo_path(), doc.read_doc::<D>()?).await?;
+        }
+        Ok(())
+    }
+
+    /// Replaces a doc with hand-edited text, which is kept exactly as given
+    pub async fn update_doc_string<D: RepoDoc + DbRecord, P: AsRef<Path>>(
+        &self,
+        path: P,
+        s: &str,
+    ) -> Result<Document> {
+        let path = path.as_ref();
+        if !self.repo.path.join(path).exists() {
+            return Err(error::io_error(io::ErrorKind::NotFound, path));
+        }
+        let record = D::parse_valid(s, path, &self.config)?;
+
+        let mut doc = Document::new(self.repo.clone(), path)?;
+        doc.write_string(s, true)?;
+        self.upsert(path, record).await?;
 
         Ok(doc)
     }
@@ -136,6 +541,7 @@ impl System {
     pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Document>> {
         let read_dir = fs::read_dir(self.repo.path.join(path)).map_err(Error::from)?;
         let docs: Vec<Document> = read_dir
+            .filter(|e| e.as_ref().map_or(true, |e| !is_template(&e.path())))
             .map(|e| {
                 let path = e
                     .unwrap()
@@ -152,10 +558,210 @@ impl System {
     pub async fn reindex<D: RepoDoc + DbRecord>(&self, path: &Path) -> Result<()> {
         let doc = Document::new(self.repo.clone(), path)?;
         let record = doc.read_doc::<D>()?;
-        self.db.upsert_record(&record).await?;
+        self.upsert(path, record).await
+    }
+
+    /// Indexes a doc along with its derived fields
+    async fn upsert<D: RepoDoc + DbRecord>(&self, path: &Path, mut record: D) -> Result<()> {
+        self.derive_fields(path, &mut record)?;
+        self.db.upsert_record(&record).await
+    }
+
+    /// Computes the fields of a doc that are only kept in the index, e.g. a task's SLA due date
+    pub fn derive_fields<D: RepoDoc>(&self, path: &Path, doc: &mut D) -> Result<()> {
+        let created = || {
+            let (first, _) = self.repo.first_and_last_changes(path)?;
+            Ok(self.repo.change(&first)?.timestamp)
+        };
+        doc.derive_fields(&self.config, &created)
+    }
+
+    /// Reindexes docs after their files changed outside of `create_doc`/`update_doc`
+    ///
+    /// Docs whose files no longer exist are removed from the index.
+    pub async fn reindex_paths<D: RepoDoc + DbRecord>(&self, paths: &[PathBuf]) -> Result<()> {
+        let (present, gone): (Vec<&PathBuf>, Vec<&PathBuf>) = paths
+            .iter()
+            .partition(|path| self.repo.path.join(path).exists());
+        // a moved doc is gone from one path and present at another, so removals go first
+        for id in gone.iter().filter_map(|path| path.file_stem()) {
+            self.db.delete_record::<D>(&id.to_string_lossy()).await?;
+        }
+        for path in present {
+            self.reindex::<D>(path).await?;
+        }
         Ok(())
     }
 
+    /// Reads every doc of a kind in a team
+    ///
+    /// Docs that fail to parse are skipped.
+    pub fn read_docs<D: Collection>(&self, team: &str) -> Result<Vec<D>> {
+        let mut docs = Vec::new();
+        for path in self.collection_paths(&D::KIND, team)? {
+            match self.read_doc::<D, _>(&path) {
+                Ok(doc) => docs.push(doc),
+                Err(err) => warn!("Skipping {}: {}", path.display(), err),
+            }
+        }
+        Ok(docs)
+    }
+
+    /// Reindexes every doc of every kind in a team, returning how many were indexed
+    pub async fn reindex_team(&self, team: &str) -> Result<usize> {
+        let mut count = 0;
+        for kind in KINDS {
+            let paths = self.collection_paths(kind, team)?;
+            (kind.reindex)(self, &paths).await?;
+            count += paths.len();
+        }
+        // archived tasks are kept outside of the tasks dir
+        let archived = self.archived_paths(team)?;
+        self.reindex_paths::<Task>(&archived).await?;
+        Ok(count + archived.len())
+    }
+
+    /// Reindexes changed files of any kind, such as the files touched by a change
+    ///
+    /// Files that aren't in a collection are ignored.
+    pub async fn reindex_files(&self, paths: &[PathBuf]) -> Result<()> {
+        for kind in KINDS {
+            let paths: Vec<PathBuf> = paths
+                .iter()
+                .filter(|path| Kind::of(path).is_some_and(|(k, _)| k == kind))
+                .cloned()
+                .collect();
+            (kind.reindex)(self, &paths).await?;
+        }
+        Ok(())
+    }
+
+    /// Docs of a kind in a team dir, relative to the repo root
+    pub fn collection_paths(&self, kind: &Kind, team: &str) -> Result<Vec<PathBuf>> {
+        let dir = kind.dir(team);
+        if !self.repo.path.join(&dir).is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut paths: Vec<PathBuf> = fs::read_dir(self.repo.path.join(&dir))?
+            .filter_map(|e| e.ok())
+            .map(|e| dir.join(e.file_name()))
+            .filter(|path| Kind::of(path).is_some_and(|(k, t)| k == kind && t == team))
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Every change recorded in the repo, newest first
+    pub fn log(&self) -> Result<Vec<ChangeInfo>> {
+        self.repo.log()
+    }
+
+    pub fn is_tracked<P: AsRef<Path>>(&self, path: P) -> Result<bool> {
+        self.repo.is_tracked(path.as_ref())
+    }
+
+    /// Adds an existing file to the repo and records it
+    pub fn track<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut repo = self.repo.clone();
+        repo.add_file(path)?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        repo.record(&format!("Added {name}"))
+    }
+
+    /// Most recent change recorded in the repo
+    pub fn latest_change(&self) -> Result<Option<ChangeInfo>> {
+        self.repo.latest_change()
+    }
+
+    /// Files touched by a change, before and after it
+    pub fn change_diff(&self, hash: &str) -> Result<ChangeDiff> {
+        self.repo.diff(hash)
+    }
+
+    /// True if the change was pushed to (or pulled from) any remote
+    pub fn is_pushed(&self, hash: &str) -> Result<bool> {
+        self.repo.is_pushed(hash)
+    }
+
+    /// Unrecords the most recent change, resetting the working copy to the state before it
+    pub fn undo(&self, hash: &str) -> Result<()> {
+        self.repo.clone().unrecord_latest(hash)
+    }
+
+    /// Records a change that reverses an earlier one, returning the new change's hash
+    pub fn revert(&self, hash: &str, msg: &str) -> Result<String> {
+        self.repo.clone().revert(hash, msg)
+    }
+
+    /// Tags the current state of the repo with a message, returning the tagged state
+    pub fn tag(&self, msg: &str) -> Result<String> {
+        self.repo.clone().tag(msg)
+    }
+
+    /// Rebuilds every revision of the documents in a directory
+    ///
+    /// Revisions are keyed by document path and ordered oldest first. Revisions
+    /// that fail to parse are skipped.
+    pub fn history<D: RepoDoc, P: AsRef<Path>>(
+        &self,
+        dir: P,
+    ) -> Result<BTreeMap<PathBuf, Vec<DocRevision<D>>>> {
+        let mut history: BTreeMap<PathBuf, Vec<DocRevision<D>>> = BTreeMap::new();
+        for revision in self.repo.history(dir.as_ref())?.into_iter().rev() {
+            for (path, contents) in revision.files {
+                let doc = match contents {
+                    None => None,
+                    Some(s) => match D::parse_doc(&s, Some(path.clone())) {
+                        Ok(doc) => Some(doc),
+                        Err(err) => {
+                            warn!("Skipping revision of {}: {}", path.display(), err);
+                            continue;
+                        }
+                    },
+                };
+                history.entry(path).or_default().push(DocRevision {
+                    change: revision.change.clone(),
+                    doc,
+                });
+            }
+        }
+        Ok(history)
+    }
+
+    /// Reads the documents in a directory as they were at a past change or time
+    ///
+    /// Documents that fail to parse are skipped.
+    pub fn read_dir_as_of<D: RepoDoc, P: AsRef<Path>>(&self, dir: P, at: &AsOf) -> Result<Vec<D>> {
+        let dir = dir.as_ref();
+        let files = self.repo.snapshot(dir, at)?;
+        let docs = files
+            .into_iter()
+            .filter(|(path, _)| path.parent() == Some(dir) && !is_template(path))
+            .filter_map(|(path, s)| match D::parse_doc(&s, Some(path.clone())) {
+                Ok(doc) => Some(doc),
+                Err(err) => {
+                    warn!("Skipping {}: {}", path.display(), err);
+                    None
+                }
+            })
+            .collect();
+        Ok(docs)
+    }
+
+    /// Runs a query against records that aren't in the index, e.g. past revisions
+    pub async fn query_records<D: DbRecord>(
+        &self,
+        records: &[D],
+        where_clause: &str,
+    ) -> Result<Vec<D>> {
+        let db = Db::memory().await?;
+        for record in records {
+            db.upsert_record(record).await?;
+        }
+        db.query_dangerous::<D>(where_clause).await
+    }
+
     pub async fn query<D: DbRecord>(&self, where_clause: &str) -> Result<Vec<D>> {
         self.db.query_dangerous::<D>(where_clause).await
     }
@@ -231,8 +837,19 @@ impl Document {
         let file_path = self.canonical_path();
         debug!("Saving {}", file_path.display());
 
+        let md = match fs::read_to_string(&file_path) {
+            Ok(old) => doc.update_doc_string(&old),
+            Err(_) => doc.to_doc_string(),
+        };
+        self.write_string(&md, record)
+    }
+
+    /// Writes doc text to disk as is (truncates if existing)
+    ///
+    /// Optionally records the change in the repository
+    pub fn write_string(&mut self, md: &str, record: bool) -> Result<()> {
+        let file_path = self.canonical_path();
         let mut file = File::create(file_path)?;
-        let md = doc.to_doc_string();
         file.write_all(md.as_bytes())?;
 
         if record {
@@ -427,4 +1044,57 @@ mod tests {
     //     assert_eq!(intersecting(12, 5), 1..=1);
     //     assert_eq!(intersecting(21, 5), 2..=2);
     // }
+
+    #[test]
+    fn test_field_line() {
+        let doc = "---\ntitle: hello\ntype: bug\nprops:\n  estimate: 3\n  severity: S9\n---\n";
+        assert_eq!(field_line(doc, "type"), Some(3));
+        assert_eq!(field_line(doc, "props.severity"), Some(6));
+        assert_eq!(field_line(doc, "props.repro"), Some(4));
+        assert_eq!(field_line(doc, "due"), None);
+
+        let doc = "+++\ntitle = \"hello\"\ntype = \"bug\"\n\n[props]\nseverity = \"S9\"\n+++\n";
+        assert_eq!(field_line(doc, "type"), Some(3));
+        assert_eq!(field_line(doc, "props.severity"), Some(6));
+
+        let doc = "title \"hello\"\nprops {\n    severity \"S9\"\n}\ndescription \"type: bug\"\n";
+        assert_eq!(field_line(doc, "props.severity"), Some(3));
+        assert_eq!(field_line(doc, "type"), None);
+    }
+
+    #[tokio::test]
+    async fn test_prepare_archive_skips_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join(".pijul").join(repo::PRISTINE_DIR)).unwrap();
+        fs::create_dir_all(dir.path().join("DIV/tasks")).unwrap();
+        fs::write(
+            dir.path().join("DIV/tasks/DIV-1.md"),
+            "---\ntitle: one\n---\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("DIV/tasks/DIV-2.md"),
+            "---\ntitle: two\ntype: bug\n---\n",
+        )
+        .unwrap();
+        let dv = System {
+            repo: Repository::find_root(Some(dir.path().to_owned())).unwrap(),
+            db: Db::memory().await.unwrap(),
+            config: toml::from_str("[types.bug]\nrequired = [\"severity\"]\n").unwrap(),
+        };
+
+        let tasks = [
+            ("DIV-1".to_owned(), "2025-Q3".to_owned()),
+            ("DIV-2".to_owned(), "2025-Q3".to_owned()),
+        ];
+        let (moves, skipped) = dv.prepare_archive("DIV", &tasks);
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].to, Path::new("DIV/archive/2025-Q3/DIV-1.md"));
+        assert!(moves[0].doc.contains("a-2025-Q3"));
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0, "DIV-2");
+        assert!(matches!(skipped[0].1, Error::Invalid { .. }));
+        // nothing is moved until every task has been checked
+        assert!(dir.path().join("DIV/tasks/DIV-1.md").exists());
+    }
 }
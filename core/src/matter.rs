@@ -0,0 +1,299 @@
+//! Format-preserving edits of YAML and TOML front matter
+//!
+//! Rewrites only the top-level keys whose values changed, so unknown keys, comments,
+//! key order and the description survive edits byte-for-byte.
+
+use chrono::NaiveDate;
+use serde_yaml::{Mapping, Value};
+
+pub(crate) const DELIMITER: &str = "---";
+pub(crate) const TOML_DELIMITER: &str = "+++";
+
+/// A top-level chunk of front matter
+enum Entry<'a> {
+    /// A key with its value, including any indented lines that follow it
+    Key { key: String, text: &'a str },
+    /// Blank lines and comments between keys
+    Trivia(&'a str),
+}
+
+/// Front matter and body of a doc, split at the delimiter lines
+pub(crate) struct Split<'a> {
+    /// Everything up to and including the opening delimiter line
+    pub open: &'a str,
+    pub matter: &'a str,
+    pub close: &'a str,
+    pub body: &'a str,
+}
+
+pub(crate) fn split<'a>(s: &'a str, delimiter: &str) -> Option<Split<'a>> {
+    let mut offset = 0;
+    let mut lines = s.split_inclusive('\n');
+    let first = lines.next()?;
+    if first.trim_end() != delimiter {
+        return None;
+    }
+    offset += first.len();
+    let matter_start = offset;
+    for line in lines {
+        if line.trim_end() == delimiter {
+            let close_end = offset + line.len();
+            return Some(Split {
+                open: &s[..matter_start],
+                matter: &s[matter_start..offset],
+                close: &s[offset..close_end],
+                body: &s[close_end..],
+            });
+        }
+        offset += line.len();
+    }
+    None
+}
+
+fn entries(matter: &str) -> Vec<Entry<'_>> {
+    let mut entries = Vec::new();
+    let (mut start, mut offset) = (0, 0);
+    let mut current: Option<String> = None;
+    // start of blank lines after a key, which belong to its value if more of it follows
+    let mut blanks: Option<usize> = None;
+    for line in matter.split_inclusive('\n') {
+        let is_continuation = line.starts_with([' ', '\t', '-']) && !line.trim().is_empty();
+        let is_blank = line.trim().is_empty();
+        let is_comment = line.starts_with('#');
+        if current.is_some() && is_continuation {
+            blanks = None;
+        } else if current.is_some() && is_blank {
+            blanks.get_or_insert(offset);
+        } else if current.is_none() && (is_continuation || is_blank || is_comment) {
+            // more trivia
+        } else {
+            let end = blanks.take().unwrap_or(offset);
+            push(&mut entries, matter, start, end, current.take());
+            start = end;
+            if !is_comment {
+                push(&mut entries, matter, start, offset, None);
+                start = offset;
+                current = line.split_once(':').map(|(key, _)| unquote(key));
+            }
+        }
+        offset += line.len();
+    }
+    let end = blanks.unwrap_or(offset);
+    push(&mut entries, matter, start, end, current);
+    push(&mut entries, matter, end, offset, None);
+    entries
+}
+
+fn push<'a>(
+    entries: &mut Vec<Entry<'a>>,
+    matter: &'a str,
+    start: usize,
+    end: usize,
+    key: Option<String>,
+) {
+    if start == end {
+        return;
+    }
+    let text = &matter[start..end];
+    entries.push(match key {
+        Some(key) => Entry::Key { key, text },
+        None => Entry::Trivia(text),
+    });
+}
+
+fn unquote(key: &str) -> String {
+    key.trim()
+        .trim_matches(|c| c == '"' || c == '\'')
+        .to_owned()
+}
+
+/// Serializes a single `key: value` entry
+fn key_text(key: &Value, value: &Value) -> String {
+    let mut mapping = Mapping::new();
+    mapping.insert(key.clone(), value.clone());
+    serde_yaml::to_string(&mapping).unwrap_or_default()
+}
+
+/// Applies changed fields to a doc's text
+///
+/// `old_fields` are the fields parsed from `old`, and `new_fields` the fields to write.
+/// Keys in neither are unknown to the doc type and kept as they are, and keys missing from
+/// `old` are only added if their value changed, so defaults aren't written out. The body is
+/// kept unless a new one is given. Returns `None` if `old` has no front matter to edit.
+pub fn merge(
+    old: &str,
+    old_fields: &Mapping,
+    new_fields: &Mapping,
+    body: Option<&str>,
+) -> Option<String> {
+    let split = split(old, DELIMITER)?;
+    let entries = entries(split.matter);
+
+    let mut matter = String::new();
+    let mut written = Vec::new();
+    for entry in &entries {
+        match entry {
+            Entry::Trivia(text) => matter.push_str(text),
+            Entry::Key { key, text } => {
+                let yaml_key = Value::String(key.clone());
+                match (old_fields.get(&yaml_key), new_fields.get(&yaml_key)) {
+                    (old, Some(new)) if old == Some(new) => matter.push_str(text),
+                    (_, Some(new)) => matter.push_str(&key_text(&yaml_key, new)),
+                    // cleared field
+                    (Some(_), None) => {}
+                    // unknown to the doc type
+                    (None, None) => matter.push_str(text),
+                }
+                written.push(yaml_key);
+            }
+        }
+    }
+
+    let added: String = new_fields
+        .iter()
+        .filter(|(key, value)| !written.contains(key) && old_fields.get(*key) != Some(*value))
+        .map(|(key, value)| key_text(key, value))
+        .collect();
+    if !added.is_empty() {
+        // new keys go after the last key, ahead of trailing blank lines
+        let trailing = matter.split_off(matter.trim_end().len());
+        let trailing = match matter.is_empty() {
+            true => trailing.as_str(),
+            // the first newline ends the last line
+            false => trailing.strip_prefix('\n').unwrap_or(&trailing),
+        };
+        let trailing = trailing.to_owned();
+        if !matter.is_empty() {
+            matter.push('\n');
+        }
+        matter.push_str(&added);
+        matter.push_str(&trailing);
+    }
+
+    Some(format!(
+        "{}{}{}{}",
+        split.open,
+        matter,
+        split.close,
+        body.unwrap_or(split.body)
+    ))
+}
+
+/// Converts a YAML value to TOML, dropping nulls, which TOML can't express
+///
+/// Date strings become TOML dates.
+fn toml_value(value: &Value) -> Option<toml_edit::Value> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64()?.into(),
+        },
+        Value::String(s) if s.parse::<NaiveDate>().is_ok() => {
+            match s.parse::<toml_edit::Datetime>() {
+                Ok(date) => date.into(),
+                Err(_) => s.into(),
+            }
+        }
+        Value::String(s) => s.into(),
+        Value::Sequence(items) => items
+            .iter()
+            .filter_map(toml_value)
+            .collect::<toml_edit::Array>()
+            .into(),
+        Value::Mapping(fields) => {
+            let mut table = toml_edit::InlineTable::new();
+            for (key, value) in fields {
+                if let (Some(key), Some(value)) = (key.as_str(), toml_value(value)) {
+                    table.insert(key, value);
+                }
+            }
+            table.into()
+        }
+        Value::Tagged(tagged) => return toml_value(&tagged.value),
+    })
+}
+
+/// Converts a YAML value to a TOML item, using a `[table]` for mappings
+fn toml_item(value: &Value) -> Option<toml_edit::Item> {
+    match value {
+        Value::Mapping(fields) => {
+            let mut table = toml_edit::Table::new();
+            for (key, value) in fields {
+                if let (Some(key), Some(value)) = (key.as_str(), toml_value(value)) {
+                    table.insert(key, toml_edit::value(value));
+                }
+            }
+            Some(toml_edit::Item::Table(table))
+        }
+        value => toml_value(value).map(toml_edit::value),
+    }
+}
+
+/// Serializes fields as TOML front matter, without delimiters
+pub(crate) fn to_toml(fields: &Mapping) -> String {
+    let mut doc = toml_edit::DocumentMut::new();
+    for (key, value) in fields {
+        if let (Some(key), Some(item)) = (key.as_str(), toml_item(value)) {
+            doc.insert(key, item);
+        }
+    }
+    doc.to_string()
+}
+
+/// Applies changed fields to a doc with TOML front matter, like `merge` does for YAML
+pub fn merge_toml(
+    old: &str,
+    old_fields: &Mapping,
+    new_fields: &Mapping,
+    body: Option<&str>,
+) -> Option<String> {
+    let split = split(old, TOML_DELIMITER)?;
+    let mut doc: toml_edit::DocumentMut = split.matter.parse().ok()?;
+    merge_table(doc.as_table_mut(), old_fields, new_fields);
+    Some(format!(
+        "{}{}{}{}",
+        split.open,
+        doc,
+        split.close,
+        body.unwrap_or(split.body)
+    ))
+}
+
+fn merge_table(table: &mut toml_edit::Table, old_fields: &Mapping, new_fields: &Mapping) {
+    let keys: Vec<&Value> = old_fields.keys().chain(new_fields.keys()).collect();
+    for key in keys {
+        let Some(name) = key.as_str() else { continue };
+        let (old, new) = (old_fields.get(key), new_fields.get(key));
+        if old == new {
+            continue;
+        }
+        match (old, new) {
+            // edit tables key by key, keeping their comments
+            (Some(Value::Mapping(old)), Some(Value::Mapping(new)))
+                if table.get(name).is_some_and(toml_edit::Item::is_table) =>
+            {
+                if let Some(toml_edit::Item::Table(inner)) = table.get_mut(name) {
+                    merge_table(inner, old, new);
+                }
+            }
+            (_, Some(new)) => match (table.get_mut(name), toml_item(new)) {
+                // replace the value, keeping the key's comments
+                (Some(toml_edit::Item::Value(value)), Some(toml_edit::Item::Value(mut new))) => {
+                    *new.decor_mut() = value.decor().clone();
+                    *value = new;
+                }
+                (_, Some(item)) => {
+                    table.insert(name, item);
+                }
+                (_, None) => {
+                    table.remove(name);
+                }
+            },
+            (_, None) => {
+                table.remove(name);
+            }
+        }
+    }
+}
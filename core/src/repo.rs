@@ -4,19 +4,22 @@ use chrono::{DateTime, Utc};
 use libpijul::change::{Author, ChangeHeader, LocalChange};
 use libpijul::changestore::{self, filesystem, ChangeStore};
 use libpijul::key::{PublicKey, SecretKey};
-use libpijul::pristine::sanakirja::{Pristine, Txn};
+use libpijul::pristine::sanakirja::{MutTxn, Pristine, Txn};
 use libpijul::pristine::{self, TreeTxnT, TxnT};
 use libpijul::{
-    working_copy, Base32, ChannelMutTxnT, ChannelTxnT, DepsTxnT, GraphTxnT, MutTxnTExt, RevLog,
-    TxnTExt, DOT_DIR,
+    working_copy, ArcTxn, Archive, Base32, ChannelMutTxnT, ChannelRef, ChannelTxnT, DepsTxnT,
+    GraphTxnT, MutTxnT, MutTxnTExt, RevLog, TxnTExt, DOT_DIR,
 };
 use log::{debug, warn};
 use owning_ref::{BoxRef, OwningHandle};
 use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env::{self, current_dir};
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const IDENTITY_NAME: &str = "default";
 
@@ -39,6 +42,39 @@ pub struct ChangeInfo {
     pub message: String,
 }
 
+/// A change along with the contents before and after it of every file it touched
+#[derive(Debug, Clone)]
+pub struct ChangeDiff {
+    pub change: ChangeInfo,
+    /// Contents before and after the change, `None` where the file didn't exist
+    pub files: BTreeMap<PathBuf, (Option<String>, Option<String>)>,
+}
+
+/// Uncommitted transaction, the forked channel, and its change hashes newest first
+type HistoryFork = (
+    ArcTxn<MutTxn<()>>,
+    ChannelRef<MutTxn<()>>,
+    Vec<libpijul::Hash>,
+);
+
+/// A point in the repo's history
+#[derive(Debug, Clone)]
+pub enum AsOf {
+    /// The state after the last change recorded at or before this time
+    Time(DateTime<Utc>),
+    /// The state after a change, identified by its hash or a prefix of it
+    Change(String),
+}
+
+/// A change along with the files it modified under some prefix
+#[derive(Debug, Clone)]
+pub struct Revision {
+    pub change: ChangeInfo,
+    /// Contents after the change, keyed by path relative to the repo root.
+    /// `None` if the change deleted the file.
+    pub files: BTreeMap<PathBuf, Option<String>>,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct Identity {
     pub display_name: String,
@@ -84,8 +120,27 @@ pub const DEFAULT_CHANNEL: &str = "main";
 pub const PRISTINE_DIR: &str = "pristine";
 pub const CHANGES_DIR: &str = "changes";
 pub const CONFIG_FILE: &str = "config";
+/// Scratch channel used to rebuild history; only ever exists in uncommitted transactions
+const HISTORY_CHANNEL: &str = "divvee-history";
 // const DEFAULT_IGNORE: [&[u8]; 2] = [b".git", b".DS_Store"];
 
+/// Marks the working copy as in sync with the channel since `oldest`, so the next record
+/// only diffs files modified after it
+fn touch_channel(txn: &mut MutTxn<()>, channel: &ChannelRef<MutTxn<()>>, oldest: SystemTime) {
+    let mut oldest = oldest.duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    if oldest == 0 {
+        // If no diff was done at all, it means that no
+        // existing file changed since last time (some
+        // files may have been added, deleted or moved,
+        // but `touch` isn't about those).
+        oldest = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+    }
+    txn.touch_channel(&mut *channel.write(), Some((oldest / 1000) * 1000));
+}
+
 impl Repository {
     fn find_dot_dir(cur: Option<PathBuf>) -> Result<PathBuf> {
         let mut cur = if let Some(cur) = cur {
@@ -171,6 +226,25 @@ impl Repository {
         Ok(())
     }
 
+    /// Moves a tracked file in the working copy and the repo, like `pijul mv`
+    ///
+    /// The move is recorded with the next change, which keeps the file's history.
+    pub fn move_file<P: AsRef<Path>>(&mut self, from: P, to: P) -> Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        debug!("move_file {} {}", from.display(), to.display());
+        if let Some(dir) = to.parent() {
+            fs::create_dir_all(self.path.join(dir))?;
+        }
+        fs::rename(self.path.join(from), self.path.join(to))?;
+
+        let txn = self.pristine.arc_txn_begin().map_err(repo_error)?;
+        txn.write()
+            .move_file(&from.to_string_lossy(), &to.to_string_lossy(), 0)
+            .map_err(repo_error)?;
+        txn.commit().map_err(repo_error)?;
+        Ok(())
+    }
+
     pub fn record(&mut self, msg: &str) -> Result<()> {
         let txn = self.pristine.arc_txn_begin().map_err(repo_error)?;
 
@@ -281,21 +355,7 @@ impl Repository {
 
         debug!("Hash: {}", hash.to_base32());
         debug!("oldest = {:?}", oldest);
-        let mut oldest = oldest
-            .duration_since(std::time::SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
-        if oldest == 0 {
-            // If no diff was done at all, it means that no
-            // existing file changed since last time (some
-            // files may have been added, deleted or moved,
-            // but `touch` isn't about those).
-            oldest = std::time::SystemTime::now()
-                .duration_since(std::time::SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64;
-        }
-        txn_.touch_channel(&mut *channel.write(), Some((oldest / 1000) * 1000));
+        touch_channel(&mut txn_, &channel, oldest);
         std::mem::drop(txn_);
         txn.commit().map_err(repo_error)?;
         Ok(())
@@ -319,7 +379,8 @@ impl Repository {
                         .map(|i| i.email.clone())
                         .ok();
                 }
-                author.unwrap()
+                // collaborators' identities may not have been pulled
+                author.unwrap_or(key)
             })
             .collect();
         Ok(ChangeInfo {
@@ -369,17 +430,420 @@ impl Repository {
             .clone();
 
         let channel = &*channel_ref.read();
-        let last_modified = txn.last_modified(&channel);
+        // log position of the newest change (the channel's last_modified time goes
+        // stale once a change is unrecorded)
+        let last_position = txn
+            .reverse_log(channel, None)
+            .map_err(repo_error)?
+            .next()
+            .transpose()
+            .map_err(repo_error)?
+            .map_or(0, |(pos, _)| pos);
 
         let mut log = txn.log_for_path(channel, inode_position, 0).unwrap();
         let first = log.next().unwrap().map_err(repo_error)?;
         let mut revlog = txn
-            .rev_log_for_path(channel, inode_position, last_modified)
+            .rev_log_for_path(channel, inode_position, last_position)
             .unwrap();
         let last = revlog.next().unwrap().map_err(repo_error)?;
         Ok((first, last))
     }
 
+    /// Rebuilds the contents of every file under `prefix` after each change, newest first
+    ///
+    /// Works by unrecording changes one at a time from a fork of the channel. The fork
+    /// lives in a transaction that is never committed, so the repository is left untouched.
+    pub fn history(&self, prefix: &Path) -> Result<Vec<Revision>> {
+        let (txn, fork, hashes) = self.fork_history()?;
+        let mut revisions = Vec::new();
+        let mut current = self.archive(&txn, &fork, prefix)?;
+        for hash in hashes {
+            txn.write()
+                .unrecord(&self.changes, &fork, &hash, 0)
+                .map_err(repo_error)?;
+            let previous = self.archive(&txn, &fork, prefix)?;
+
+            let paths: BTreeSet<&PathBuf> = current.keys().chain(previous.keys()).collect();
+            let files: BTreeMap<PathBuf, Option<String>> = paths
+                .into_iter()
+                .filter(|path| current.get(*path) != previous.get(*path))
+                .map(|path| (path.clone(), current.get(path).cloned()))
+                .collect();
+            if !files.is_empty() {
+                revisions.push(Revision {
+                    change: self.change(&hash)?,
+                    files,
+                });
+            }
+            current = previous;
+        }
+        // dropping the uncommitted transaction discards the fork
+        Ok(revisions)
+    }
+
+    /// Contents of every file under `prefix` as of a past change or time
+    ///
+    /// Like [`Repository::history`], this unrecords changes newer than `at` from a
+    /// throwaway fork of the channel. A time before the first change yields no files.
+    pub fn snapshot(&self, prefix: &Path, at: &AsOf) -> Result<BTreeMap<PathBuf, String>> {
+        let (txn, fork, hashes) = self.fork_history()?;
+        let mut found = false;
+        for hash in hashes {
+            found = match at {
+                AsOf::Change(prefix) => hash.to_base32().starts_with(prefix.as_str()),
+                AsOf::Time(time) => self.change(&hash)?.timestamp <= *time,
+            };
+            if found {
+                break;
+            }
+            txn.write()
+                .unrecord(&self.changes, &fork, &hash, 0)
+                .map_err(repo_error)?;
+        }
+        if let (false, AsOf::Change(hash)) = (found, at) {
+            bail!("Change {} not found", hash);
+        }
+        self.archive(&txn, &fork, prefix)
+    }
+
+    /// Every change in the default channel, newest first
+    pub fn log(&self) -> Result<Vec<ChangeInfo>> {
+        let (_, _, hashes) = self.fork_history()?;
+        hashes.iter().map(|hash| self.change(hash)).collect()
+    }
+
+    /// Whether a file has been added to the repo
+    pub fn is_tracked(&self, path: &Path) -> Result<bool> {
+        let txn = self.pristine.txn_begin().map_err(repo_error)?;
+        txn.is_tracked(&path.to_string_lossy()).map_err(repo_error)
+    }
+
+    /// Most recent change in the default channel
+    pub fn latest_change(&self) -> Result<Option<ChangeInfo>> {
+        let (_, _, hashes) = self.fork_history()?;
+        hashes.first().map(|hash| self.change(hash)).transpose()
+    }
+
+    /// Looks up a change by its hash, or a unique prefix of it
+    fn find_change(&self, hashes: &[libpijul::Hash], prefix: &str) -> Result<libpijul::Hash> {
+        let mut matches = hashes
+            .iter()
+            .filter(|hash| hash.to_base32().starts_with(prefix));
+        match (matches.next(), matches.next()) {
+            (Some(hash), None) => Ok(*hash),
+            (Some(_), Some(_)) => Err(error::msg(format!("Change prefix {prefix} is ambiguous"))),
+            (None, _) => Err(error::msg(format!("Change {prefix} not found"))),
+        }
+    }
+
+    /// Rebuilds the files a change touched as they were before and after it
+    pub fn diff(&self, hash: &str) -> Result<ChangeDiff> {
+        let (txn, fork, hashes) = self.fork_history()?;
+        let target = self.find_change(&hashes, hash)?;
+        for hash in hashes.iter().take_while(|h| **h != target) {
+            txn.write()
+                .unrecord(&self.changes, &fork, hash, 0)
+                .map_err(repo_error)?;
+        }
+        let after = self.archive(&txn, &fork, Path::new(""))?;
+        txn.write()
+            .unrecord(&self.changes, &fork, &target, 0)
+            .map_err(repo_error)?;
+        let before = self.archive(&txn, &fork, Path::new(""))?;
+
+        let paths: BTreeSet<&PathBuf> = before.keys().chain(after.keys()).collect();
+        let files = paths
+            .into_iter()
+            .filter(|path| before.get(*path) != after.get(*path))
+            .map(|path| {
+                let contents = (before.get(path).cloned(), after.get(path).cloned());
+                (path.clone(), contents)
+            })
+            .collect();
+        Ok(ChangeDiff {
+            change: self.change(&target)?,
+            files,
+        })
+    }
+
+    /// True if any remote known to the repo has the change
+    pub fn is_pushed(&self, hash: &str) -> Result<bool> {
+        let txn = self.pristine.txn_begin().map_err(repo_error)?;
+        let hash = libpijul::Hash::from_base32(hash.as_bytes())
+            .ok_or_else(|| error::msg(format!("Invalid change hash {}", hash)))?;
+        for remote in txn
+            .iter_remotes(&pristine::RemoteId::nil())
+            .map_err(repo_error)?
+        {
+            let remote = remote.map_err(repo_error)?;
+            if txn
+                .remote_has_change(&remote, &hash.into())
+                .map_err(repo_error)?
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Removes the most recent change from the channel and resets the working copy to match
+    ///
+    /// Fails unless `hash` is the most recent change, since older changes may have dependents.
+    pub fn unrecord_latest(&mut self, hash: &str) -> Result<()> {
+        let paths: Vec<PathBuf> = self.diff(hash)?.files.into_keys().collect();
+
+        let txn = self.pristine.arc_txn_begin().map_err(repo_error)?;
+        let channel = txn
+            .read()
+            .load_channel(DEFAULT_CHANNEL)
+            .map_err(repo_error)?;
+        let channel = if let Some(channel) = channel {
+            channel
+        } else {
+            bail!("Channel {:?} not found", DEFAULT_CHANNEL);
+        };
+        let latest: Option<libpijul::Hash> = {
+            let txn_ = txn.read();
+            let channel_ = channel.read();
+            let mut log = txn_.reverse_log(&channel_, None).map_err(repo_error)?;
+            log.next()
+                .transpose()
+                .map_err(repo_error)?
+                .map(|(_, (hash, _))| hash.into())
+        };
+        let latest = match latest {
+            Some(latest) if latest.to_base32().starts_with(hash) => latest,
+            _ => {
+                return Err(error::msg(format!(
+                    "Change {hash} is not the most recent change"
+                )))
+            }
+        };
+
+        txn.write()
+            .unrecord(&self.changes, &channel, &latest, 0)
+            .map_err(repo_error)?;
+        self.output(&txn, &channel, &paths)?;
+        touch_channel(&mut txn.write(), &channel, SystemTime::now());
+        txn.commit().map_err(repo_error)?;
+        Ok(())
+    }
+
+    /// Records a change that undoes `hash`, and updates the working copy to match
+    ///
+    /// Returns the hash of the new change.
+    #[allow(clippy::result_large_err)]
+    pub fn revert(&mut self, hash: &str, msg: &str) -> Result<String> {
+        let paths: Vec<PathBuf> = self.diff(hash)?.files.into_keys().collect();
+        let txn = self.pristine.arc_txn_begin().map_err(repo_error)?;
+        let channel = txn
+            .read()
+            .load_channel(DEFAULT_CHANNEL)
+            .map_err(repo_error)?
+            .ok_or_else(|| error::msg(format!("Channel {:?} not found", DEFAULT_CHANNEL)))?;
+        let hashes: Vec<libpijul::Hash> = {
+            let txn_ = txn.read();
+            let channel_ = channel.read();
+            txn_.reverse_log(&channel_, None)
+                .map_err(repo_error)?
+                .map(|res| res.map(|(_, (hash, _))| hash.into()))
+                .collect::<std::result::Result<_, _>>()
+                .map_err(repo_error)?
+        };
+        let target = self.find_change(&hashes, hash)?;
+
+        let ident = Identity::load_global(IDENTITY_NAME)?;
+        let author = Author([("key".to_string(), ident.public_key.key)].into());
+        let header = ChangeHeader {
+            message: msg.to_string(),
+            authors: vec![author],
+            description: None,
+            timestamp: Utc::now(),
+        };
+        let key = ident.secret_key.unwrap().load(None).map_err(repo_error)?;
+
+        let change = self.changes.get_change(&target).map_err(repo_error)?;
+        let mut inverse = change.inverse(&target, header, Vec::new());
+        let hash = self
+            .changes
+            .save_change(&mut inverse, |change, hash| {
+                change.unhashed = Some(serde_json::json!({
+                    "signature": key.sign_raw(&hash.to_bytes()).unwrap(),
+                }));
+                Ok::<_, filesystem::Error>(())
+            })
+            .map_err(repo_error)?;
+        txn.write()
+            .apply_local_change(&channel, &inverse, &hash, &Default::default())
+            .map_err(repo_error)?;
+        self.output(&txn, &channel, &paths)?;
+        // the files were just written out, so nothing on disk is older than now
+        touch_channel(&mut txn.write(), &channel, SystemTime::now());
+        txn.commit().map_err(repo_error)?;
+        Ok(hash.to_base32())
+    }
+
+    /// Tags the current state of the default channel, like `pijul tag create`
+    ///
+    /// Returns the tagged state.
+    #[allow(clippy::result_large_err)]
+    pub fn tag(&mut self, msg: &str) -> Result<String> {
+        let txn = self.pristine.arc_txn_begin().map_err(repo_error)?;
+        let channel = txn
+            .read()
+            .load_channel(DEFAULT_CHANNEL)
+            .map_err(repo_error)?
+            .ok_or_else(|| error::msg(format!("Channel {:?} not found", DEFAULT_CHANNEL)))?;
+        let last = {
+            let txn_ = txn.read();
+            let channel_ = channel.read();
+            let last = txn_
+                .reverse_log(&channel_, None)
+                .map_err(repo_error)?
+                .next()
+                .transpose()
+                .map_err(repo_error)?;
+            let Some((n, _)) = last else {
+                bail!("Nothing recorded to tag");
+            };
+            n
+        };
+        if txn
+            .read()
+            .is_tagged(txn.read().tags(&channel.read()), last)
+            .map_err(repo_error)?
+        {
+            bail!("The current state is already tagged");
+        }
+
+        let ident = Identity::load_global(IDENTITY_NAME)?;
+        let author = Author([("key".to_string(), ident.public_key.key)].into());
+        let header = ChangeHeader {
+            message: msg.to_string(),
+            authors: vec![author],
+            description: None,
+            timestamp: Utc::now(),
+        };
+        let mut contents = Vec::new();
+        let state =
+            libpijul::tag::from_channel(&*txn.read(), DEFAULT_CHANNEL, &header, &mut contents)
+                .map_err(repo_error)?;
+        let path = self.changes.tag_filename(&state);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, contents)?;
+
+        {
+            let mut txn_ = txn.write();
+            let mut channel_ = channel.write();
+            let tags = txn_.tags_mut(&mut channel_);
+            txn_.put_tags(tags, last, &state).map_err(repo_error)?;
+        }
+        txn.commit().map_err(repo_error)?;
+        Ok(state.to_base32())
+    }
+
+    /// Writes files from the channel out to the working copy, removing those it no longer has
+    fn output(
+        &self,
+        txn: &ArcTxn<MutTxn<()>>,
+        channel: &ChannelRef<MutTxn<()>>,
+        paths: &[PathBuf],
+    ) -> Result<()> {
+        let files = self.archive(txn, channel, Path::new(""))?;
+        // tracked paths the channel no longer has a file at, which may have been moved back
+        let mut gone = Vec::new();
+        for path in paths.iter().filter(|path| !files.contains_key(*path)) {
+            if txn
+                .read()
+                .is_tracked(&path.to_string_lossy())
+                .map_err(repo_error)?
+            {
+                gone.push(path);
+            }
+        }
+        for path in paths {
+            let full_path = self.path.join(path);
+            match files.get(path) {
+                Some(contents) => {
+                    if let Some(dir) = full_path.parent() {
+                        fs::create_dir_all(dir)?;
+                    }
+                    fs::write(full_path, contents)?;
+                    let tracked = txn
+                        .read()
+                        .is_tracked(&path.to_string_lossy())
+                        .map_err(repo_error)?;
+                    let from = gone
+                        .iter()
+                        .position(|from| from.file_name() == path.file_name());
+                    if let (false, Some(i)) = (tracked, from) {
+                        txn.write()
+                            .move_file(
+                                &gone.remove(i).to_string_lossy(),
+                                &path.to_string_lossy(),
+                                0,
+                            )
+                            .map_err(repo_error)?;
+                    }
+                }
+                None if full_path.is_file() => fs::remove_file(full_path)?,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Forks the default channel in a transaction that is never committed
+    ///
+    /// Returns the fork along with the hashes of its changes, newest first.
+    fn fork_history(&self) -> Result<HistoryFork> {
+        let txn = self.pristine.arc_txn_begin().map_err(repo_error)?;
+        let channel = txn
+            .read()
+            .load_channel(DEFAULT_CHANNEL)
+            .map_err(repo_error)?;
+        let channel = if let Some(channel) = channel {
+            channel
+        } else {
+            bail!("Channel {:?} not found", DEFAULT_CHANNEL);
+        };
+        let fork = txn
+            .write()
+            .fork(&channel, HISTORY_CHANNEL)
+            .map_err(repo_error)?;
+        let hashes: Vec<libpijul::Hash> = {
+            let txn_ = txn.read();
+            let channel_ = fork.read();
+            txn_.reverse_log(&channel_, None)
+                .map_err(repo_error)?
+                .map(|res| res.map(|(_, (hash, _))| hash.into()))
+                .collect::<std::result::Result<_, _>>()
+                .map_err(repo_error)?
+        };
+        Ok((txn, fork, hashes))
+    }
+
+    /// Outputs the files under `prefix` in the given channel
+    fn archive(
+        &self,
+        txn: &ArcTxn<MutTxn<()>>,
+        channel: &ChannelRef<MutTxn<()>>,
+        prefix: &Path,
+    ) -> Result<BTreeMap<PathBuf, String>> {
+        let mut archive = MemoryArchive::default();
+        let mut components = prefix.iter().filter_map(|c| c.to_str());
+        txn.archive_prefix(&self.changes, channel, &mut components, &mut archive)
+            .map_err(repo_error)?;
+        Ok(archive
+            .files
+            .into_iter()
+            .filter(|(path, _)| path.starts_with(prefix))
+            .map(|(path, bytes)| (path, String::from_utf8_lossy(&bytes).into_owned()))
+            .collect())
+    }
+
     // pub fn txn(&self) -> Result<Txn> {
     //     let txn = self.pristine.txn_begin().map_err(repo_error)?;
     //     Ok(txn)
@@ -508,3 +972,44 @@ impl<'txn> Iterator for LogIter<'txn> {
         None
     }
 }
+
+/// Archive that keeps output files in memory
+#[derive(Default)]
+struct MemoryArchive {
+    files: BTreeMap<PathBuf, Vec<u8>>,
+}
+
+struct MemoryFile {
+    path: PathBuf,
+    buf: Vec<u8>,
+}
+
+impl io::Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Archive for MemoryArchive {
+    type File = MemoryFile;
+    type Error = io::Error;
+
+    fn create_file(&mut self, path: &str, _mtime: u64, _perm: u16) -> MemoryFile {
+        MemoryFile {
+            path: PathBuf::from(path),
+            buf: Vec::new(),
+        }
+    }
+
+    fn create_dir(&mut self, _path: &str, _mtime: u64, _perm: u16) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn close_file(&mut self, file: MemoryFile) -> io::Result<()> {
+        self.files.insert(file.path, file.buf);
+        Ok(())
+    }
+}
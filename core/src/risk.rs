@@ -0,0 +1,185 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use crate::format::{self, DocFormat, FieldsDoc};
+use crate::{error, Error, RepoDoc};
+use serde::{Deserialize, Serialize};
+
+/// A risk someone spotted, with who watches it and how it's mitigated
+///
+/// Labels scope the risk to a milestone or epic, e.g. `m-launch`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, sqlx::FromRow)]
+pub struct Risk {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub status: RiskStatus,
+    #[serde(default)]
+    pub severity: RiskLevel,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// What is being done to make the risk less likely or less costly
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mitigation: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[sqlx(skip)]
+    pub labels: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum RiskStatus {
+    #[default]
+    Open,
+    Mitigated,
+    Closed,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum RiskLevel {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl RiskStatus {
+    pub const ALL: [RiskStatus; 3] = [RiskStatus::Open, RiskStatus::Mitigated, RiskStatus::Closed];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskStatus::Open => "open",
+            RiskStatus::Mitigated => "mitigated",
+            RiskStatus::Closed => "closed",
+        }
+    }
+}
+
+impl fmt::Display for RiskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for RiskStatus {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<RiskStatus, Error> {
+        RiskStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| error::msg(format!("Unknown risk status {s}")))
+    }
+}
+
+impl RiskLevel {
+    pub const ALL: [RiskLevel; 3] = [RiskLevel::Low, RiskLevel::Medium, RiskLevel::High];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskLevel::Low => "low",
+            RiskLevel::Medium => "medium",
+            RiskLevel::High => "high",
+        }
+    }
+}
+
+impl fmt::Display for RiskLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for RiskLevel {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<RiskLevel, Error> {
+        RiskLevel::ALL
+            .into_iter()
+            .find(|level| level.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| error::msg(format!("Unknown risk level {s}")))
+    }
+}
+
+impl Risk {
+    pub fn new(title: &str) -> Risk {
+        Risk {
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn id(&self) -> Option<String> {
+        self.id.clone()
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.status == RiskStatus::Open
+    }
+}
+
+impl FieldsDoc for Risk {
+    const BODY: &'static str = "description";
+    const KDL_LISTS: &'static [&'static str] = &["labels"];
+
+    fn body(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    fn set_body(&mut self, body: String) {
+        self.description = Some(body);
+    }
+
+    fn set_id(&mut self, id: Option<String>) {
+        self.id = id;
+    }
+}
+
+impl RepoDoc for Risk {
+    fn parse_doc(s: &str, path: Option<PathBuf>) -> Result<Self, Error> {
+        format::parse_doc(s, path)
+    }
+
+    fn update_doc_string(&self, old: &str) -> String {
+        format::update_doc(self, old)
+    }
+
+    fn to_doc_string(&self) -> String {
+        self.to_doc_string_as(DocFormat::Yaml)
+    }
+
+    fn to_doc_string_as(&self, format: DocFormat) -> String {
+        format::write_doc(self, format)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let s = "---\ntitle: Vendor API slips\nseverity: high\nlabels: [m-launch]\n---\n\nThey missed the last two dates.\n";
+        let path = PathBuf::from("DIV/risks/vendor-api-slips.md");
+        let mut risk = Risk::parse_doc(s, Some(path)).unwrap();
+        assert_eq!(risk.id().as_deref(), Some("vendor-api-slips"));
+        assert_eq!(
+            (risk.status, risk.severity),
+            (RiskStatus::Open, RiskLevel::High)
+        );
+
+        risk.status = RiskStatus::Mitigated;
+        risk.mitigation = Some("Stub the API".into());
+        assert_eq!(
+            risk.update_doc_string(s),
+            "---\ntitle: Vendor API slips\nseverity: high\nlabels: [m-launch]\nstatus: mitigated\nmitigation: Stub the API\n---\n\nThey missed the last two dates.\n"
+        );
+    }
+}
@@ -1,7 +1,9 @@
-use std::{collections::BTreeMap, path::PathBuf};
+use std::{collections::BTreeMap, fmt, path::PathBuf, str::FromStr};
 
-use crate::{repo::ChangeInfo, Error, RepoDoc};
-use gray_matter::{engine::YAML, Matter};
+use crate::config::RepoConfig;
+use crate::format::{self, DocFormat, FieldsDoc};
+use crate::{error, repo::ChangeInfo, Error, RepoDoc, Violation};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, sqlx::FromRow)]
@@ -10,10 +12,27 @@ pub struct Task {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     id: Option<String>,
     pub title: String,
+    /// Task type (e.g. bug or feature), which may require extra props
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(rename = "type")]
+    pub kind: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub status: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub assignee: Option<String>,
+    #[serde(default, skip_serializing_if = "Priority::is_none")]
+    pub priority: Priority,
+    /// Explicit backlog position; lower ranks come first within a priority
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<NaiveDate>,
+    /// Due date from the repo's SLA rules when there's no explicit one. Computed when the
+    /// task is indexed and never written to the doc
+    #[serde(skip)]
+    pub sla_due: Option<NaiveDate>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     #[sqlx(skip)]
     pub labels: Vec<String>,
@@ -30,6 +49,80 @@ pub struct Task {
     // pub updated: Option<ChangeInfo>,
 }
 
+/// Task priority, ordered from least to most urgent
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+)]
+#[serde(try_from = "String")]
+#[repr(i64)]
+pub enum Priority {
+    #[default]
+    None = 0,
+    Low = 1,
+    Medium = 2,
+    High = 3,
+    Urgent = 4,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 5] = [
+        Priority::None,
+        Priority::Low,
+        Priority::Medium,
+        Priority::High,
+        Priority::Urgent,
+    ];
+
+    pub fn is_none(&self) -> bool {
+        *self == Priority::None
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::None => "None",
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+            Priority::Urgent => "Urgent",
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Priority {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Priority, Error> {
+        Priority::ALL
+            .into_iter()
+            .find(|p| p.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| error::msg(format!("Unknown priority {s}")))
+    }
+}
+
+// docs are hand-written, so accept any case like `dv edit -p` does
+impl TryFrom<String> for Priority {
+    type Error = Error;
+    fn try_from(s: String) -> Result<Priority, Error> {
+        s.parse()
+    }
+}
+
 impl Task {
     pub fn new(title: &str) -> Task {
         let title = title.into();
@@ -42,27 +135,123 @@ impl Task {
     pub fn id(&self) -> Option<String> {
         self.id.clone()
     }
+
+    /// Whether a prop is set to something other than null or ""
+    pub fn has_prop(&self, name: &str) -> bool {
+        self.props
+            .get(name)
+            .is_some_and(|v| !v.is_null() && v.as_str() != Some(""))
+    }
+
+    /// Sets registered props that have a default and aren't set yet
+    pub fn apply_defaults(&mut self, config: &RepoConfig) {
+        for (name, field) in &config.fields {
+            if let Some(default) = field.default_value() {
+                if !self.has_prop(name) {
+                    self.props.insert(name.clone(), default);
+                }
+            }
+        }
+    }
+}
+
+impl FieldsDoc for Task {
+    const BODY: &'static str = "description";
+    const KDL_LISTS: &'static [&'static str] = &["labels"];
+
+    fn body(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    fn set_body(&mut self, body: String) {
+        self.description = Some(body);
+    }
+
+    fn set_id(&mut self, id: Option<String>) {
+        self.id = id;
+    }
 }
 
 impl RepoDoc for Task {
     fn parse_doc(s: &str, path: Option<PathBuf>) -> Result<Self, Error> {
-        let matter = Matter::<YAML>::new();
-        let res = matter.parse(s);
-        let mut doc = res.data.unwrap().deserialize::<Task>()?;
+        format::parse_doc(s, path)
+    }
 
-        if !res.content.is_empty() {
-            doc.description = Some(res.content);
+    fn derive_fields(
+        &mut self,
+        config: &RepoConfig,
+        created: &dyn Fn() -> Result<DateTime<Utc>, Error>,
+    ) -> Result<(), Error> {
+        let has_rule = config
+            .sla
+            .iter()
+            .any(|rule| self.labels.iter().any(|label| rule.matches(label)));
+        self.sla_due = match self.due.is_none() && has_rule {
+            true => config.sla_due(self, created()?),
+            false => None,
+        };
+        Ok(())
+    }
+
+    fn validate(&self, config: &RepoConfig) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        for (name, value) in &self.props {
+            let problem = match config.fields.get(name) {
+                Some(field) => field.check(value),
+                None if !config.fields.is_empty() => Some("is not a registered field".to_owned()),
+                None => None,
+            };
+            if let Some(problem) = problem {
+                violations.push(Violation::new(format!("props.{name}"), problem));
+            }
+        }
+
+        // required props, with the type that requires them
+        let mut required: BTreeMap<&str, Option<&str>> = config
+            .fields
+            .iter()
+            .filter(|(_, field)| field.required)
+            .map(|(name, _)| (name.as_str(), None))
+            .collect();
+        if let Some(kind) = &self.kind {
+            match config.types.get(kind) {
+                Some(task_type) => {
+                    for name in &task_type.required {
+                        required.insert(name, Some(kind));
+                    }
+                }
+                None if !config.types.is_empty() => {
+                    let known: Vec<&str> = config.types.keys().map(String::as_str).collect();
+                    violations.push(Violation::new(
+                        "type",
+                        format!("{kind} is not one of {}", known.join(", ")),
+                    ));
+                }
+                None => {}
+            }
+        }
+        for (name, kind) in required {
+            if !self.has_prop(name) {
+                let message = match kind {
+                    Some(kind) => format!("is required for {kind} tasks"),
+                    None => "is required".to_owned(),
+                };
+                violations.push(Violation::new(format!("props.{name}"), message));
+            }
         }
-        doc.id = path.and_then(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()));
-        Ok(doc)
+        violations
+    }
+
+    fn update_doc_string(&self, old: &str) -> String {
+        format::update_doc(self, old)
     }
 
     fn to_doc_string(&self) -> String {
-        let mut doc = self.clone();
-        doc.id = None;
-        let description = doc.description.take().unwrap_or_else(String::new);
-        let yaml = serde_yaml::to_string(&doc).unwrap();
-        format!("---\n{}\n---\n\n{}", yaml, description)
+        self.to_doc_string_as(DocFormat::Yaml)
+    }
+
+    fn to_doc_string_as(&self, format: DocFormat) -> String {
+        format::write_doc(self, format)
     }
 }
 
@@ -88,4 +277,154 @@ mod test {
         let task = Task::parse_doc("---\ntitle: hello\n---\n\ndescription", None).unwrap();
         assert_eq!(task.description.unwrap(), "description");
     }
+
+    #[test]
+    fn test_task_body_trim() {
+        let task = Task::parse_doc("---\ntitle: hello\n---\n\n\n\nSome text\n\n", None).unwrap();
+        assert_eq!(task.description.unwrap(), "Some text\n\n");
+        let task = Task::parse_doc("---\ntitle: hello\n---\n\n\n", None).unwrap();
+        assert_eq!(task.description, None);
+    }
+
+    #[test]
+    fn test_task_priority() {
+        let task =
+            Task::parse_doc("---\ntitle: hello\npriority: High\nrank: 2048\n---", None).unwrap();
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.rank, Some(2048));
+        assert!(task.priority > Priority::Medium);
+        assert_eq!("urgent".parse::<Priority>().unwrap(), Priority::Urgent);
+        let task = Task::parse_doc("---\ntitle: hello\npriority: high\n---", None).unwrap();
+        assert_eq!(task.priority, Priority::High);
+        assert!(Task::parse_doc("---\ntitle: hello\npriority: soon\n---", None).is_err());
+        assert_eq!(
+            Task::new("hello").to_doc_string().trim(),
+            "---\ntitle: hello\n\n---"
+        );
+    }
+
+    #[test]
+    fn test_task_dates() {
+        let task = Task::parse_doc("---\ntitle: hello\ndue: 2026-10-31\n---", None).unwrap();
+        assert_eq!(task.due, NaiveDate::from_ymd_opt(2026, 10, 31));
+        assert_eq!(task.start, None);
+        assert!(task.to_doc_string().contains("due: 2026-10-31\n"));
+    }
+
+    #[test]
+    fn test_task_type() {
+        let config: RepoConfig =
+            toml::from_str("[types.bug]\nrequired = [\"severity\"]\n\n[types.feature]\n").unwrap();
+        let mut task = Task::parse_doc("---\ntitle: hello\ntype: bug\n---", None).unwrap();
+        assert_eq!(task.kind.as_deref(), Some("bug"));
+        assert_eq!(
+            task.validate(&config),
+            vec![Violation::new(
+                "props.severity",
+                "is required for bug tasks"
+            )]
+        );
+        task.props.insert("severity".into(), "S2".into());
+        assert!(task.validate(&config).is_empty());
+        task.kind = Some("spike".into());
+        assert_eq!(task.validate(&config)[0].field, "type");
+        assert!(task.validate(&RepoConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_task_parse_errors() {
+        let path = PathBuf::from("DIV/tasks/DIV-1.md");
+        let parse_error = |s: &str| match Task::parse_doc(s, Some(path.clone())) {
+            Err(Error::Parse(err)) => err,
+            other => panic!("expected a parse error, got {other:?}"),
+        };
+
+        let err = parse_error("just some notes");
+        assert_eq!(err.path.as_ref(), Some(&path));
+        assert_eq!(err.line, None);
+
+        let err = parse_error("---\ntitle: hello\nrank: high\n---\n");
+        assert_eq!(err.line, Some(3));
+        assert_eq!(err.field.as_deref(), Some("rank"));
+        assert!(err.to_string().starts_with("DIV/tasks/DIV-1.md:3:"));
+
+        let err = parse_error("---\nstatus: Todo\n---\n");
+        assert_eq!(err.field.as_deref(), Some("title"));
+    }
+
+    #[test]
+    fn test_task_update_preserves_format() {
+        let old = "---\n# triaged on monday\ntitle: hello\nowner_team: infra # not a task field\nlabels:\n- a\n\n- b\nstatus: Todo\n\n---\n\nSome *text*  \n\n\n";
+        let mut task = Task::parse_doc(old, None).unwrap();
+        assert_eq!(task.labels, vec!["a", "b"]);
+        assert_eq!(task.update_doc_string(old), old);
+
+        task.status = Some("Done".into());
+        task.priority = Priority::High;
+        assert_eq!(
+            task.update_doc_string(old),
+            "---\n# triaged on monday\ntitle: hello\nowner_team: infra # not a task field\nlabels:\n- a\n\n- b\nstatus: Done\npriority: High\n\n---\n\nSome *text*  \n\n\n"
+        );
+
+        task.labels.clear();
+        task.description = Some("new".into());
+        assert_eq!(
+            task.update_doc_string(old),
+            "---\n# triaged on monday\ntitle: hello\nowner_team: infra # not a task field\nstatus: Done\npriority: High\n\n---\n\nnew"
+        );
+    }
+
+    #[test]
+    fn test_task_toml() {
+        let old = "+++\n# from hugo\ntitle = \"hello\"\nowner_team = \"infra\"\ndue = 2026-10-31\n\n[props]\nseverity = \"S2\" # initial guess\n+++\n\nSome text\n";
+        let mut task = Task::parse_doc(old, None).unwrap();
+        assert_eq!(task.due, NaiveDate::from_ymd_opt(2026, 10, 31));
+        assert_eq!(task.props["severity"], "S2");
+        assert_eq!(task.description.as_deref(), Some("Some text\n"));
+
+        task.status = Some("done".into());
+        task.props.insert("estimate".into(), 3.into());
+        assert_eq!(
+            task.update_doc_string(old),
+            "+++\n# from hugo\ntitle = \"hello\"\nowner_team = \"infra\"\ndue = 2026-10-31\nstatus = \"done\"\n\n[props]\nseverity = \"S2\" # initial guess\nestimate = 3\n+++\n\nSome text\n"
+        );
+
+        let s = task.to_doc_string_as(DocFormat::Toml);
+        assert!(s.starts_with("+++\ntitle = \"hello\"\n"));
+        assert_eq!(Task::parse_doc(&s, None).unwrap(), task);
+
+        let err = Task::parse_doc("+++\ntitle = hello\n+++\n", None).unwrap_err();
+        assert!(err.to_string().starts_with("2:9: "), "{err}");
+    }
+
+    #[test]
+    fn test_task_kdl() {
+        let old = "// imported\ntitle \"hello\"\nlabels \"bug\"\ndue \"2026-10-31\"\nprops {\n    links \"DIV-1\" \"DIV-2\"\n}\ndescription r\"\nSome text\n\"\n";
+        let path = PathBuf::from("DIV/tasks/DIV-4.kdl");
+        let mut task = Task::parse_doc(old, Some(path.clone())).unwrap();
+        assert_eq!(task.id().as_deref(), Some("DIV-4"));
+        assert_eq!(task.labels, vec!["bug"]);
+        assert_eq!(task.due, NaiveDate::from_ymd_opt(2026, 10, 31));
+        assert_eq!(task.description.as_deref(), Some("\nSome text\n"));
+
+        task.status = Some("todo".into());
+        task.labels.push("c-acme".into());
+        assert_eq!(
+            task.update_doc_string(old),
+            "// imported\ntitle \"hello\"\nlabels \"bug\" \"c-acme\"\ndue \"2026-10-31\"\nprops {\n    links \"DIV-1\" \"DIV-2\"\n}\nstatus \"todo\"\ndescription r\"\nSome text\n\"\n"
+        );
+
+        let s = task.to_doc_string_as(DocFormat::Kdl);
+        let mut parsed = Task::parse_doc(&s, Some(path.clone())).unwrap();
+        assert_eq!(parsed.labels, task.labels);
+        parsed.id = None;
+        task.id = None;
+        assert_eq!(parsed, task);
+
+        let err = Task::parse_doc("title \"hello\"\nstatus {", Some(path)).unwrap_err();
+        assert!(
+            err.to_string().starts_with("DIV/tasks/DIV-4.kdl:2:"),
+            "{err}"
+        );
+    }
 }
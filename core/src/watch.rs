@@ -0,0 +1,46 @@
+use crate::Result;
+use chrono::{DateTime, Utc};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Local, untracked dir holding each identity's watch list
+const WATCH_DIR: &str = ".divvee/watch";
+
+/// Tasks an identity is watching, and when it last read its inbox
+///
+/// Watch lists are personal, so they live in the local checkout rather than in the
+/// shared history.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatchList {
+    #[serde(default)]
+    pub tasks: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_read: Option<DateTime<Utc>>,
+}
+
+impl WatchList {
+    pub fn load(root: &Path, email: &str) -> Result<WatchList> {
+        let path = Self::path(root, email);
+        if !path.exists() {
+            return Ok(WatchList::default());
+        }
+        debug!("Loading watch list from {}", path.display());
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, root: &Path, email: &str) -> Result<()> {
+        let path = Self::path(root, email);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn path(root: &Path, email: &str) -> PathBuf {
+        root.join(WATCH_DIR).join(format!("{email}.toml"))
+    }
+}